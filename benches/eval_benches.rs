@@ -70,7 +70,7 @@ fn generate(buf: &mut String, depth: u32, start: u32, op: Op) {
 
 fn bench_eval(c: &mut Criterion) {
     let mut group = c.benchmark_group("eval");
-    for depth in [5, 10] {
+    for depth in [5, 10, 15] {
         let expr = generate_expr(depth);
 
         group.throughput(Throughput::Bytes(expr.len() as u64));
@@ -81,5 +81,65 @@ fn bench_eval(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_eval);
+fn bench_eval_one_pass(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval_one_pass");
+    for depth in [5, 10, 15] {
+        let expr = generate_expr(depth);
+
+        group.throughput(Throughput::Bytes(expr.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &expr, |b, expr| {
+            b.iter(|| sari::eval_one_pass(expr));
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for depth in [5, 10, 15] {
+        let expr = generate_expr(depth);
+
+        group.throughput(Throughput::Bytes(expr.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &expr, |b, expr| {
+            b.iter(|| sari::parse(expr));
+        });
+    }
+    group.finish();
+}
+
+fn bench_eval_parsed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval_parsed");
+    for depth in [5, 10] {
+        let expr = sari::parse(&generate_expr(depth)).unwrap();
+        let bytecode = expr.to_bytecode();
+        let compiled = expr.compile();
+
+        group.bench_with_input(BenchmarkId::new("tree", depth), &expr, |b, expr| {
+            b.iter(|| expr.eval());
+        });
+        group.bench_with_input(
+            BenchmarkId::new("bytecode", depth),
+            &bytecode,
+            |b, bytecode| {
+                b.iter(|| bytecode.eval());
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("closures", depth),
+            &compiled,
+            |b, compiled| {
+                b.iter(|| compiled.eval());
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_eval,
+    bench_eval_one_pass,
+    bench_parse,
+    bench_eval_parsed
+);
 criterion_main!(benches);
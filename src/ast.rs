@@ -1,16 +1,443 @@
+//! Abstract syntax tree.
+//!
+//! The tree is stored in an [`Ast`] arena. Nodes refer to their children using
+//! [`ExprId`]s, which are indices into the arena.
+//!
+//! # Examples
+//!
+//! ```
+//! use sari::ast::{BinaryOp, Expr};
+//!
+//! let expr = sari::parse("1 + 2")?;
+//! let ast = expr.ast();
+//!
+//! let Expr::Binary(binary) = &ast[ast.root()] else {
+//!     panic!("expected a binary expression");
+//! };
+//! assert_eq!(binary.op, BinaryOp::Add);
+//! # Ok::<(), sari::Error>(())
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Index;
+
 use crate::source::{Span, Spanned};
 use crate::token::{Token, TokenKind};
 
+/// Identifier of an expression in an [`Ast`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ExprId(u32);
+
+impl ExprId {
+    fn new(index: usize) -> ExprId {
+        ExprId(u32::try_from(index).expect("too many expressions"))
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Arena holding expressions of an abstract syntax tree.
+///
+/// Expressions are added using the [`int`](Ast::int), [`group`](Ast::group),
+/// and [`binary`](Ast::binary) methods and accessed by indexing the arena with
+/// their [`ExprId`]. Because an expression can only refer to expressions that
+/// already exist, children are always added before their parents. The last
+/// added expression is the root of the tree.
+///
+/// Two ASTs are equal if their trees are equal, regardless of how the
+/// expressions are laid out in the arena.
+///
+/// The [`Display`](fmt::Display) implementation prints the tree as a valid
+/// expression. See [`display`](Ast::display) for details. An empty `Ast` is
+/// displayed as an empty string.
+///
+/// # Examples
+///
+/// ```
+/// use sari::Span;
+/// use sari::ast::{Ast, BinaryOp, Expr};
+///
+/// let mut ast = Ast::new();
+/// let left = ast.int(Span::new(0, 1), 1);
+/// let right = ast.int(Span::new(4, 5), 2);
+/// let root = ast.binary(Span::new(0, 5), BinaryOp::Add, left, right);
+///
+/// assert_eq!(ast.root(), root);
+/// assert!(matches!(ast[left], Expr::Int(_)));
+/// ```
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RawAst")
+)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+}
+
+impl Ast {
+    /// Creates a new empty `Ast`.
+    pub fn new() -> Ast {
+        Ast { exprs: vec![] }
+    }
+
+    /// Returns `true` if the AST has no expressions.
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /// Returns the root expression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the AST is [empty](Ast::is_empty).
+    pub fn root(&self) -> ExprId {
+        assert!(!self.exprs.is_empty(), "empty AST");
+
+        ExprId::new(self.exprs.len() - 1)
+    }
+
+    /// Adds an integer literal and returns its identifier.
+    pub fn int(&mut self, span: Span, value: i32) -> ExprId {
+        self.add(Expr::Int(IntExpr { span, value }))
+    }
+
+    /// Adds a parenthesized expression and returns its identifier.
+    pub fn group(&mut self, span: Span, expr: ExprId) -> ExprId {
+        self.add(Expr::Group(GroupExpr { span, expr }))
+    }
+
+    /// Adds a binary expression and returns its identifier.
+    pub fn binary(&mut self, span: Span, op: BinaryOp, left: ExprId, right: ExprId) -> ExprId {
+        self.add(Expr::Binary(BinaryExpr {
+            span,
+            op,
+            left,
+            right,
+        }))
+    }
+
+    /// Returns an object that displays an expression as a valid expression
+    /// source.
+    ///
+    /// Parentheses are printed only where the precedence and associativity of
+    /// the operators require them, regardless of any [`GroupExpr`]s in the
+    /// tree. Negative integer literals, which can't be written directly, are
+    /// printed as a subtraction from zero, which evaluates to the same value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::Span;
+    /// use sari::ast::{Ast, BinaryOp};
+    ///
+    /// let span = Span::new(0, 0);
+    ///
+    /// let mut ast = Ast::new();
+    /// let one = ast.int(span, 1);
+    /// let two = ast.int(span, 2);
+    /// let three = ast.int(span, -3);
+    /// let sub = ast.binary(span, BinaryOp::Sub, two, three);
+    /// let root = ast.binary(span, BinaryOp::Sub, one, sub);
+    ///
+    /// assert_eq!(ast.display(sub).to_string(), "2 - (0 - 3)");
+    /// assert_eq!(ast.display(root).to_string(), "1 - (2 - (0 - 3))");
+    /// assert_eq!(ast.to_string(), "1 - (2 - (0 - 3))");
+    /// ```
+    pub fn display(&self, id: ExprId) -> impl fmt::Display + '_ {
+        DisplayExpr { ast: self, id }
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.exprs.truncate(len);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub(crate) fn ids(&self) -> impl Iterator<Item = ExprId> + use<> {
+        (0..self.exprs.len()).map(ExprId::new)
+    }
+
+    // Returns an iterator walking an expression and its children depth-first,
+    // left to right. Each expression is entered before its children and left
+    // after them.
+    //
+    // Trees can be arbitrarily deep (e.g. long chains of operators), so code
+    // walking them uses this iterator instead of recursion.
+    pub(crate) fn walk(&self, id: ExprId) -> Walk<'_> {
+        Walk {
+            ast: self,
+            stack: vec![Step::Enter(id)],
+            children: 0,
+        }
+    }
+
+    fn add(&mut self, expr: Expr) -> ExprId {
+        let id = ExprId::new(self.exprs.len());
+        self.exprs.push(expr);
+
+        id
+    }
+
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>, id: ExprId) -> fmt::Result {
+        // Pieces are printed from the top of the stack, so the ones printed
+        // later are pushed first.
+        let mut stack = vec![Piece::Expr(id, Parent::None)];
+
+        while let Some(piece) = stack.pop() {
+            let (id, parent) = match piece {
+                Piece::Expr(id, parent) => (self.skip_groups(id), parent),
+                Piece::Op(op) => {
+                    write!(f, " {} ", op.as_str())?;
+                    continue;
+                }
+                Piece::RParen => {
+                    f.write_str(")")?;
+                    continue;
+                }
+            };
+
+            let parens = match (self.precedence(id), parent) {
+                (Some(precedence), Parent::Left(op)) => precedence < op.precedence(),
+                (Some(precedence), Parent::Right(op)) => precedence <= op.precedence(),
+                _ => false,
+            };
+
+            if parens {
+                f.write_str("(")?;
+                stack.push(Piece::RParen);
+            }
+
+            match &self[id] {
+                Expr::Int(expr) if expr.value < 0 => {
+                    // `unsigned_abs` handles `i32::MIN`, whose literal wraps
+                    // back to the same value.
+                    write!(f, "0 - {}", expr.value.unsigned_abs())?;
+                }
+                Expr::Int(expr) => write!(f, "{}", expr.value)?,
+                Expr::Group(_) => unreachable!("groups are skipped"),
+                Expr::Binary(expr) => {
+                    stack.push(Piece::Expr(expr.right, Parent::Right(expr.op)));
+                    stack.push(Piece::Op(expr.op));
+                    stack.push(Piece::Expr(expr.left, Parent::Left(expr.op)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn skip_groups(&self, mut id: ExprId) -> ExprId {
+        while let Expr::Group(expr) = &self[id] {
+            id = expr.expr;
+        }
+
+        id
+    }
+
+    // Returns the precedence of an expression printed without parentheses, or
+    // `None` if it never needs them.
+    fn precedence(&self, id: ExprId) -> Option<u8> {
+        match &self[self.skip_groups(id)] {
+            Expr::Int(expr) if expr.value < 0 => Some(BinaryOp::Sub.precedence()),
+            Expr::Int(_) => None,
+            Expr::Group(_) => unreachable!("groups are skipped"),
+            Expr::Binary(expr) => Some(expr.op.precedence()),
+        }
+    }
+
+    fn tree_eq(&self, id: ExprId, other: &Ast, other_id: ExprId) -> bool {
+        let mut stack = vec![(id, other_id)];
+
+        while let Some((id, other_id)) = stack.pop() {
+            let eq = match (&self[id], &other[other_id]) {
+                (Expr::Int(a), Expr::Int(b)) => a == b,
+                (Expr::Group(a), Expr::Group(b)) => {
+                    stack.push((a.expr, b.expr));
+                    a.span == b.span
+                }
+                (Expr::Binary(a), Expr::Binary(b)) => {
+                    stack.push((a.left, b.left));
+                    stack.push((a.right, b.right));
+                    a.span == b.span && a.op == b.op
+                }
+                _ => false,
+            };
+
+            if !eq {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()]
+    }
+}
+
+// Deserialized arena, which needs to be validated before it becomes an `Ast`.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawAst {
+    exprs: Vec<Expr>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawAst> for Ast {
+    type Error = alloc::string::String;
+
+    fn try_from(raw: RawAst) -> Result<Ast, Self::Error> {
+        // Children must precede their parents, which also rules out cycles.
+        for (index, expr) in raw.exprs.iter().enumerate() {
+            let children = match expr {
+                Expr::Int(_) => vec![],
+                Expr::Group(expr) => vec![expr.expr],
+                Expr::Binary(expr) => vec![expr.left, expr.right],
+            };
+
+            if let Some(child) = children.iter().find(|child| child.index() >= index) {
+                return Err(alloc::format!(
+                    "expression {index} refers to invalid expression {}",
+                    child.index()
+                ));
+            }
+        }
+
+        Ok(Ast { exprs: raw.exprs })
+    }
+}
+
+impl fmt::Display for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        self.display(self.root()).fmt(f)
+    }
+}
+
+// Position of an expression relative to its parent.
+#[derive(Copy, Clone)]
+enum Parent {
+    None,
+    Left(BinaryOp),
+    Right(BinaryOp),
+}
+
+// Piece of an expression waiting to be printed.
+enum Piece {
+    Expr(ExprId, Parent),
+    Op(BinaryOp),
+    RParen,
+}
+
+struct DisplayExpr<'a> {
+    ast: &'a Ast,
+    id: ExprId,
+}
+
+impl fmt::Display for DisplayExpr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.ast.fmt_expr(f, self.id)
+    }
+}
+
+// Step of a traversal of an expression tree, see `Ast::walk`.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub(crate) enum Step {
+    Enter(ExprId),
+    Leave(ExprId),
+}
+
+pub(crate) struct Walk<'a> {
+    ast: &'a Ast,
+    stack: Vec<Step>,
+    // Number of children of the last entered expression, which are on top of
+    // the stack.
+    children: usize,
+}
+
+impl Walk<'_> {
+    // Skips the children of the last entered expression. The expression is
+    // still left.
+    pub(crate) fn skip_children(&mut self) {
+        self.stack.truncate(self.stack.len() - self.children);
+        self.children = 0;
+    }
+}
+
+impl Iterator for Walk<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let step = self.stack.pop()?;
+        self.children = 0;
+
+        if let Step::Enter(id) = step {
+            self.stack.push(Step::Leave(id));
+
+            match &self.ast[id] {
+                Expr::Int(_) => {}
+                Expr::Group(expr) => {
+                    self.stack.push(Step::Enter(expr.expr));
+                    self.children = 1;
+                }
+                Expr::Binary(expr) => {
+                    self.stack.push(Step::Enter(expr.right));
+                    self.stack.push(Step::Enter(expr.left));
+                    self.children = 2;
+                }
+            }
+        }
+
+        Some(step)
+    }
+}
+
+impl Eq for Ast {}
+
+impl PartialEq for Ast {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.exprs.is_empty(), other.exprs.is_empty()) {
+            (true, true) => true,
+            (false, false) => self.tree_eq(self.root(), other, other.root()),
+            _ => false,
+        }
+    }
+}
+
+/// Binary operator.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum BinaryOp {
+    /// Addition (`+`).
     Add,
+    /// Subtraction (`-`).
     Sub,
+    /// Multiplication (`*`).
     Mul,
+    /// Division (`/`).
     Div,
 }
 
 impl BinaryOp {
-    pub fn from_token(token: Token) -> BinaryOp {
+    pub(crate) fn from_token(token: Token) -> BinaryOp {
         match token.kind() {
             TokenKind::Plus => BinaryOp::Add,
             TokenKind::Minus => BinaryOp::Sub,
@@ -19,11 +446,49 @@ impl BinaryOp {
             _ => panic!("not a binary operator: {token:?}"),
         }
     }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        }
+    }
+
+    // Precedence levels follow the grammar in the parser, where additive
+    // operators bind less tightly than multiplicative ones. All operators are
+    // left-associative.
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div => 2,
+        }
+    }
+
+    pub(crate) fn apply(&self, left: i32, right: i32) -> Option<i32> {
+        match self {
+            BinaryOp::Add => Some(left.wrapping_add(right)),
+            BinaryOp::Sub => Some(left.wrapping_sub(right)),
+            BinaryOp::Mul => Some(left.wrapping_mul(right)),
+            BinaryOp::Div => {
+                if right == 0 {
+                    return None;
+                }
+
+                Some(left.wrapping_div(right))
+            }
+        }
+    }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+/// Integer literal.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IntExpr {
+    /// Span of the literal.
     pub span: Span,
+    /// Value of the literal.
     pub value: i32,
 }
 
@@ -33,10 +498,14 @@ impl Spanned for IntExpr {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+/// Parenthesized expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GroupExpr {
+    /// Span of the expression, including the parentheses.
     pub span: Span,
-    pub expr: Box<Expr>,
+    /// Expression inside the parentheses.
+    pub expr: ExprId,
 }
 
 impl Spanned for GroupExpr {
@@ -45,12 +514,18 @@ impl Spanned for GroupExpr {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+/// Binary expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinaryExpr {
+    /// Span of the expression.
     pub span: Span,
+    /// Operator.
     pub op: BinaryOp,
-    pub left: Box<Expr>,
-    pub right: Box<Expr>,
+    /// Left operand.
+    pub left: ExprId,
+    /// Right operand.
+    pub right: ExprId,
 }
 
 impl Spanned for BinaryExpr {
@@ -59,47 +534,238 @@ impl Spanned for BinaryExpr {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+/// Expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
 pub enum Expr {
+    /// Integer literal.
     Int(IntExpr),
+    /// Parenthesized expression.
     Group(GroupExpr),
+    /// Binary expression.
     Binary(BinaryExpr),
 }
 
 impl Expr {
-    pub fn int(span: Span, value: i32) -> Box<Expr> {
-        Box::new(Expr::Int(IntExpr { span, value }))
+    /// Returns the span.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Int(expr) => expr.span,
+            Expr::Group(expr) => expr.span,
+            Expr::Binary(expr) => expr.span,
+        }
     }
+}
 
-    pub fn group(span: Span, expr: Box<Expr>) -> Box<Expr> {
-        Box::new(Expr::Group(GroupExpr { span, expr }))
+impl Spanned for Expr {
+    fn span(&self) -> Span {
+        Expr::span(self)
     }
+}
 
-    pub fn binary(span: Span, op: BinaryOp, left: Box<Expr>, right: Box<Expr>) -> Box<Expr> {
-        Box::new(Expr::Binary(BinaryExpr {
-            span,
-            op,
-            left,
-            right,
-        }))
+// Builds ASTs from nested function calls, which is more readable in tests than
+// adding expressions one by one.
+#[cfg(test)]
+pub(crate) mod build {
+    use alloc::boxed::Box;
+
+    use super::*;
+
+    pub(crate) type Builder = Box<dyn FnOnce(&mut Ast) -> ExprId>;
+
+    pub(crate) fn ast(builder: Builder) -> Ast {
+        let mut ast = Ast::new();
+        builder(&mut ast);
+
+        ast
     }
-}
 
-impl Spanned for Expr {
-    fn span(&self) -> Span {
-        match self {
-            Expr::Int(expr) => expr.span,
-            Expr::Group(expr) => expr.span,
-            Expr::Binary(expr) => expr.span,
-        }
+    pub(crate) fn int(span: Span, value: i32) -> Builder {
+        Box::new(move |ast| ast.int(span, value))
+    }
+
+    pub(crate) fn group(span: Span, expr: Builder) -> Builder {
+        Box::new(move |ast| {
+            let expr = expr(ast);
+
+            ast.group(span, expr)
+        })
+    }
+
+    pub(crate) fn binary(span: Span, op: BinaryOp, left: Builder, right: Builder) -> Builder {
+        Box::new(move |ast| {
+            let left = left(ast);
+            let right = right(ast);
+
+            ast.binary(span, op, left, right)
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
+
+    use super::build::{ast, binary, group, int};
     use super::*;
     use crate::source::Span;
 
+    #[test]
+    fn ast_root_works() {
+        let mut ast = Ast::new();
+        let left = ast.int(Span::new(0, 1), 1);
+        let right = ast.int(Span::new(4, 5), 2);
+        let root = ast.binary(Span::new(0, 5), BinaryOp::Add, left, right);
+
+        assert_eq!(ast.root(), root);
+    }
+
+    #[test]
+    fn ast_eq_works() {
+        let mut ast_1 = Ast::new();
+        let left = ast_1.int(Span::new(0, 1), 1);
+        let right = ast_1.int(Span::new(4, 5), 2);
+        ast_1.binary(Span::new(0, 5), BinaryOp::Add, left, right);
+
+        // different layout
+        let mut ast_2 = Ast::new();
+        let right = ast_2.int(Span::new(4, 5), 2);
+        let left = ast_2.int(Span::new(0, 1), 1);
+        ast_2.binary(Span::new(0, 5), BinaryOp::Add, left, right);
+
+        // unreachable expressions
+        let mut ast_3 = Ast::new();
+        ast_3.int(Span::new(8, 9), 3);
+        let left = ast_3.int(Span::new(0, 1), 1);
+        let right = ast_3.int(Span::new(4, 5), 2);
+        ast_3.binary(Span::new(0, 5), BinaryOp::Add, left, right);
+
+        assert_eq!(ast_1, ast_2);
+        assert_eq!(ast_1, ast_3);
+        assert_eq!(Ast::new(), Ast::new());
+
+        assert_ne!(
+            ast_1,
+            ast(binary(
+                Span::new(0, 5),
+                BinaryOp::Sub,
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
+            )),
+        );
+        assert_ne!(
+            ast_1,
+            ast(group(
+                Span::new(0, 5),
+                binary(
+                    Span::new(0, 5),
+                    BinaryOp::Add,
+                    int(Span::new(0, 1), 1),
+                    int(Span::new(4, 5), 2),
+                ),
+            )),
+        );
+        assert_ne!(ast_1, Ast::new());
+    }
+
+    #[test]
+    fn ast_display_works() {
+        let span = Span::new(0, 0);
+
+        assert_eq!(Ast::new().to_string(), "");
+        assert_eq!(ast(int(span, 1)).to_string(), "1");
+        assert_eq!(ast(group(span, int(span, 1))).to_string(), "1");
+
+        // precedence
+        assert_eq!(
+            ast(binary(
+                span,
+                BinaryOp::Add,
+                binary(span, BinaryOp::Mul, int(span, 1), int(span, 2)),
+                int(span, 3),
+            ))
+            .to_string(),
+            "1 * 2 + 3",
+        );
+        assert_eq!(
+            ast(binary(
+                span,
+                BinaryOp::Mul,
+                binary(span, BinaryOp::Add, int(span, 1), int(span, 2)),
+                int(span, 3),
+            ))
+            .to_string(),
+            "(1 + 2) * 3",
+        );
+        assert_eq!(
+            ast(binary(
+                span,
+                BinaryOp::Add,
+                int(span, 1),
+                binary(span, BinaryOp::Mul, int(span, 2), int(span, 3)),
+            ))
+            .to_string(),
+            "1 + 2 * 3",
+        );
+
+        // associativity
+        assert_eq!(
+            ast(binary(
+                span,
+                BinaryOp::Sub,
+                binary(span, BinaryOp::Sub, int(span, 1), int(span, 2)),
+                int(span, 3),
+            ))
+            .to_string(),
+            "1 - 2 - 3",
+        );
+        assert_eq!(
+            ast(binary(
+                span,
+                BinaryOp::Sub,
+                int(span, 1),
+                group(
+                    span,
+                    binary(span, BinaryOp::Sub, int(span, 2), int(span, 3)),
+                ),
+            ))
+            .to_string(),
+            "1 - (2 - 3)",
+        );
+
+        // groups are ignored
+        assert_eq!(
+            ast(binary(
+                span,
+                BinaryOp::Add,
+                group(
+                    span,
+                    binary(span, BinaryOp::Mul, int(span, 1), int(span, 2)),
+                ),
+                int(span, 3),
+            ))
+            .to_string(),
+            "1 * 2 + 3",
+        );
+
+        // negative literals
+        assert_eq!(ast(int(span, -1)).to_string(), "0 - 1");
+        assert_eq!(ast(int(span, i32::MIN)).to_string(), "0 - 2147483648");
+        assert_eq!(
+            ast(binary(span, BinaryOp::Mul, int(span, -1), int(span, 2))).to_string(),
+            "(0 - 1) * 2",
+        );
+        assert_eq!(
+            ast(binary(span, BinaryOp::Sub, int(span, 1), int(span, -2))).to_string(),
+            "1 - (0 - 2)",
+        );
+        assert_eq!(
+            ast(binary(span, BinaryOp::Add, int(span, -1), int(span, 2))).to_string(),
+            "0 - 1 + 2",
+        );
+    }
+
     #[test]
     fn binary_op_from_token_works() {
         let plus = Token::plus(Span::new(0, 1));
@@ -112,4 +778,28 @@ mod tests {
         assert_eq!(BinaryOp::from_token(star), BinaryOp::Mul);
         assert_eq!(BinaryOp::from_token(slash), BinaryOp::Div);
     }
+
+    #[test]
+    fn binary_op_precedence_works() {
+        assert_eq!(BinaryOp::Add.precedence(), BinaryOp::Sub.precedence());
+        assert_eq!(BinaryOp::Mul.precedence(), BinaryOp::Div.precedence());
+        assert!(BinaryOp::Add.precedence() < BinaryOp::Mul.precedence());
+    }
+
+    #[test]
+    fn binary_op_apply_works() {
+        assert_eq!(BinaryOp::Add.apply(1, 2), Some(3));
+        assert_eq!(BinaryOp::Sub.apply(3, 2), Some(1));
+        assert_eq!(BinaryOp::Mul.apply(2, 3), Some(6));
+        assert_eq!(BinaryOp::Div.apply(6, 3), Some(2));
+
+        // overflow
+        assert_eq!(BinaryOp::Add.apply(i32::MAX, 1), Some(i32::MIN));
+        assert_eq!(BinaryOp::Sub.apply(i32::MIN, 1), Some(i32::MAX));
+        assert_eq!(BinaryOp::Mul.apply(i32::MIN, -1), Some(i32::MIN));
+        assert_eq!(BinaryOp::Div.apply(i32::MIN, -1), Some(i32::MIN));
+
+        // division by zero
+        assert_eq!(BinaryOp::Div.apply(1, 0), None);
+    }
 }
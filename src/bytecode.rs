@@ -0,0 +1,61 @@
+use alloc::sync::Arc;
+
+use crate::error::Error;
+use crate::program::Program;
+use crate::source::SourceMap;
+use crate::vm::Vm;
+
+/// Expression compiled to bytecode.
+///
+/// Bytecode is created by [`Expression::to_bytecode`](crate::Expression::to_bytecode).
+/// It is evaluated by a stack-based virtual machine, which is faster than
+/// evaluating the expression directly. This makes it suitable for expressions
+/// that are evaluated many times.
+///
+/// # Examples
+///
+/// ```
+/// let bytecode = sari::parse("(1 + 2) * 3")?.to_bytecode();
+///
+/// assert_eq!(bytecode.eval(), Ok(9));
+/// # Ok::<(), sari::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Bytecode {
+    program: Program,
+    source_map: Arc<SourceMap>,
+}
+
+impl Bytecode {
+    pub(crate) fn new(program: Program, source_map: Arc<SourceMap>) -> Bytecode {
+        Bytecode {
+            program,
+            source_map,
+        }
+    }
+
+    /// Evaluates the bytecode and returns the result.
+    ///
+    /// The result is the same as the result of evaluating the expression the
+    /// bytecode was compiled from, including any errors, except that
+    /// [`Limits::max_steps`](crate::Limits::max_steps) isn't enforced. An
+    /// expression that exceeds the limit evaluates successfully here.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the evaluation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let bytecode = sari::parse("(1 + 2) * 3")?.to_bytecode();
+    /// assert_eq!(bytecode.eval(), Ok(9));
+    ///
+    /// let bytecode = sari::parse("1 / 0")?.to_bytecode();
+    /// assert_eq!(bytecode.eval().unwrap_err().message(), "division by zero");
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn eval(&self) -> Result<i32, Error> {
+        Vm::new(&self.program, &self.source_map).run()
+    }
+}
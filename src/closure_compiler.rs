@@ -0,0 +1,230 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ast::{Ast, BinaryExpr, BinaryOp, Expr, ExprId, GroupExpr, IntExpr};
+use crate::error::Error;
+use crate::source::SourceMap;
+
+pub type Closure = Box<dyn Fn() -> Result<i32, Error> + Send + Sync>;
+
+pub struct ClosureCompiler<'a> {
+    ast: &'a Ast,
+    source_map: &'a SourceMap,
+}
+
+impl<'a> ClosureCompiler<'a> {
+    pub fn new(ast: &'a Ast, source_map: &'a SourceMap) -> ClosureCompiler<'a> {
+        ClosureCompiler { ast, source_map }
+    }
+
+    pub fn compile(&self) -> Closure {
+        self.compile_expr(self.ast.root())
+    }
+
+    fn compile_expr(&self, id: ExprId) -> Closure {
+        match &self.ast[id] {
+            Expr::Int(expr) => self.compile_int_expr(expr),
+            Expr::Group(expr) => self.compile_group_expr(expr),
+            Expr::Binary(expr) => self.compile_binary_expr(expr),
+        }
+    }
+
+    fn compile_int_expr(&self, expr: &IntExpr) -> Closure {
+        let value = expr.value;
+
+        Box::new(move || Ok(value))
+    }
+
+    fn compile_group_expr(&self, expr: &GroupExpr) -> Closure {
+        self.compile_expr(expr.expr)
+    }
+
+    fn compile_binary_expr(&self, expr: &BinaryExpr) -> Closure {
+        // Chains of operators (e.g. `1 + 2 + 3`) nest to the left, so they are
+        // compiled into a single closure applying the operators in a loop.
+        // This way, compiling and calling closures only recurses into right
+        // operands and parentheses.
+        let mut chain = vec![expr];
+        while let Expr::Binary(left) = &self.ast[chain[chain.len() - 1].left] {
+            chain.push(left);
+        }
+
+        let first = self.compile_expr(chain[chain.len() - 1].left);
+        let rest: Vec<_> = chain
+            .iter()
+            .rev()
+            .map(|expr| {
+                // The error is created upfront, so that the closure doesn't
+                // need to hold the source map. Only division can fail.
+                let error = (expr.op == BinaryOp::Div)
+                    .then(|| self.source_map.error(expr.span, "division by zero"));
+
+                (expr.op, self.compile_expr(expr.right), error)
+            })
+            .collect();
+
+        Box::new(move || {
+            let mut value = first()?;
+
+            for (op, right, error) in &rest {
+                let right = right()?;
+
+                value = op
+                    .apply(value, right)
+                    .ok_or_else(|| error.clone().expect("operation can't fail"))?;
+            }
+
+            Ok(value)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::build::{ast, binary, group, int};
+    use crate::source::{SourcePos, SourceSpan, Span};
+
+    macro_rules! assert_evals {
+        ($ast:expr, $value:expr $(,)?) => {
+            let source_map = SourceMap::new();
+
+            let ast = ast($ast);
+            let closure = ClosureCompiler::new(&ast, &source_map).compile();
+
+            assert_eq!(closure(), Ok($value));
+        };
+    }
+
+    macro_rules! assert_does_not_eval {
+        ($ast:expr, $error:expr $(,)?) => {
+            let source_map = SourceMap::new();
+
+            let ast = ast($ast);
+            let closure = ClosureCompiler::new(&ast, &source_map).compile();
+
+            assert_eq!(closure(), Err($error));
+        };
+    }
+
+    #[test]
+    fn compiles_int_expr() {
+        assert_evals!(int(Span::new(0, 1), 1), 1);
+    }
+
+    #[test]
+    fn compiles_group_expr() {
+        assert_evals!(group(Span::new(0, 3), int(Span::new(1, 2), 1)), 1);
+    }
+
+    #[test]
+    fn compiles_binary_expr_add() {
+        assert_evals!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Add,
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
+            ),
+            3,
+        );
+
+        // overflow
+        assert_evals!(
+            binary(
+                Span::new(0, 14),
+                BinaryOp::Add,
+                int(Span::new(0, 10), 2147483647),
+                int(Span::new(13, 14), 1),
+            ),
+            -2147483648,
+        );
+    }
+
+    #[test]
+    fn compiles_binary_expr_sub() {
+        assert_evals!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Sub,
+                int(Span::new(0, 1), 3),
+                int(Span::new(4, 5), 2),
+            ),
+            1,
+        );
+
+        // overflow
+        assert_evals!(
+            binary(
+                Span::new(0, 15),
+                BinaryOp::Sub,
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 15), 1),
+            ),
+            2147483647,
+        );
+    }
+
+    #[test]
+    fn compiles_binary_expr_mul() {
+        assert_evals!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Mul,
+                int(Span::new(0, 1), 2),
+                int(Span::new(4, 5), 3),
+            ),
+            6,
+        );
+
+        // overflow
+        assert_evals!(
+            binary(
+                Span::new(0, 16),
+                BinaryOp::Mul,
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 16), -1),
+            ),
+            -2147483648,
+        );
+    }
+
+    #[test]
+    fn compiles_binary_expr_div() {
+        assert_evals!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Div,
+                int(Span::new(0, 1), 6),
+                int(Span::new(4, 5), 3),
+            ),
+            2,
+        );
+
+        // overflow
+        assert_evals!(
+            binary(
+                Span::new(0, 16),
+                BinaryOp::Div,
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 16), -1),
+            ),
+            -2147483648,
+        );
+
+        // division by zero
+        assert_does_not_eval!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Div,
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 0),
+            ),
+            Error::new(
+                SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(5, 1, 6)),
+                "division by zero",
+            ),
+        );
+    }
+}
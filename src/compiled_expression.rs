@@ -0,0 +1,79 @@
+use core::fmt;
+
+use crate::closure_compiler::Closure;
+use crate::error::Error;
+
+/// Expression compiled to closures.
+///
+/// A compiled expression is created by [`Expression::compile`](crate::Expression::compile).
+/// Each node of the expression is turned into a closure calling the closures
+/// of its children. Calling the closures is faster than evaluating the
+/// expression directly, which makes compiled expressions suitable for
+/// expressions that are evaluated many times.
+///
+/// A compiled expression is immutable and it is `Send` and `Sync`, so it can
+/// be evaluated from many threads at once without locking.
+///
+/// # Examples
+///
+/// ```
+/// let compiled = sari::parse("(1 + 2) * 3")?.compile();
+///
+/// assert_eq!(compiled.eval(), Ok(9));
+/// # Ok::<(), sari::Error>(())
+/// ```
+///
+/// Evaluating from many threads:
+///
+/// ```
+/// use std::thread;
+///
+/// let compiled = sari::parse("(1 + 2) * 3")?.compile();
+///
+/// thread::scope(|scope| {
+///     for _ in 0..4 {
+///         scope.spawn(|| assert_eq!(compiled.eval(), Ok(9)));
+///     }
+/// });
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub struct CompiledExpression {
+    closure: Closure,
+}
+
+impl CompiledExpression {
+    pub(crate) fn new(closure: Closure) -> CompiledExpression {
+        CompiledExpression { closure }
+    }
+
+    /// Evaluates the compiled expression and returns the result.
+    ///
+    /// The result is the same as the result of evaluating the expression it
+    /// was compiled from, including any errors, except that
+    /// [`Limits::max_steps`](crate::Limits::max_steps) isn't enforced. An
+    /// expression that exceeds the limit evaluates successfully here.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the evaluation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let compiled = sari::parse("(1 + 2) * 3")?.compile();
+    /// assert_eq!(compiled.eval(), Ok(9));
+    ///
+    /// let compiled = sari::parse("1 / 0")?.compile();
+    /// assert_eq!(compiled.eval().unwrap_err().message(), "division by zero");
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn eval(&self) -> Result<i32, Error> {
+        (self.closure)()
+    }
+}
+
+impl fmt::Debug for CompiledExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompiledExpression").finish_non_exhaustive()
+    }
+}
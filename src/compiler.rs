@@ -0,0 +1,188 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ast::{Ast, BinaryExpr, Expr, ExprId, GroupExpr, IntExpr, Step};
+use crate::program::{Instruction, Program};
+use crate::source::Span;
+
+pub struct Compiler<'a> {
+    ast: &'a Ast,
+    instructions: Vec<Instruction>,
+    spans: Vec<Span>,
+    stack_depth: usize,
+    stack_size: usize,
+}
+
+impl Compiler<'_> {
+    pub fn new(ast: &Ast) -> Compiler<'_> {
+        Compiler {
+            ast,
+            instructions: vec![],
+            spans: vec![],
+            stack_depth: 0,
+            stack_size: 0,
+        }
+    }
+
+    pub fn compile(mut self) -> Program {
+        self.compile_expr(self.ast.root());
+
+        Program {
+            instructions: self.instructions,
+            spans: self.spans,
+            stack_size: self.stack_size,
+        }
+    }
+
+    fn compile_expr(&mut self, id: ExprId) {
+        let ast = self.ast;
+
+        // Instructions are emitted in postfix order, once the children of an
+        // expression are compiled.
+        for step in ast.walk(id) {
+            if let Step::Leave(id) = step {
+                match &ast[id] {
+                    Expr::Int(expr) => self.compile_int_expr(expr),
+                    Expr::Group(expr) => self.compile_group_expr(expr),
+                    Expr::Binary(expr) => self.compile_binary_expr(expr),
+                }
+            }
+        }
+    }
+
+    fn compile_int_expr(&mut self, expr: &IntExpr) {
+        self.emit(Instruction::Int(expr.value), expr.span);
+        self.push();
+    }
+
+    fn compile_group_expr(&mut self, _expr: &GroupExpr) {}
+
+    fn compile_binary_expr(&mut self, expr: &BinaryExpr) {
+        self.emit(Instruction::Binary(expr.op), expr.span);
+        self.pop();
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Span) {
+        self.instructions.push(instruction);
+        self.spans.push(span);
+    }
+
+    fn push(&mut self) {
+        self.stack_depth += 1;
+        self.stack_size = self.stack_size.max(self.stack_depth);
+    }
+
+    fn pop(&mut self) {
+        self.stack_depth -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::BinaryOp;
+    use crate::ast::build::{ast, binary, group, int};
+
+    macro_rules! assert_compiles {
+        ($ast:expr, $program:expr $(,)?) => {
+            let ast = ast($ast);
+            let compiler = Compiler::new(&ast);
+
+            assert_eq!(compiler.compile(), $program);
+        };
+    }
+
+    #[test]
+    fn compiles_int_expr() {
+        assert_compiles!(
+            int(Span::new(0, 1), 1),
+            Program {
+                instructions: vec![Instruction::Int(1)],
+                spans: vec![Span::new(0, 1)],
+                stack_size: 1,
+            },
+        );
+    }
+
+    #[test]
+    fn compiles_group_expr() {
+        assert_compiles!(
+            group(Span::new(0, 3), int(Span::new(1, 2), 1)),
+            Program {
+                instructions: vec![Instruction::Int(1)],
+                spans: vec![Span::new(1, 2)],
+                stack_size: 1,
+            },
+        );
+    }
+
+    #[test]
+    fn compiles_binary_expr() {
+        for op in [BinaryOp::Add, BinaryOp::Sub, BinaryOp::Mul, BinaryOp::Div] {
+            assert_compiles!(
+                binary(
+                    Span::new(0, 5),
+                    op,
+                    int(Span::new(0, 1), 1),
+                    int(Span::new(4, 5), 2),
+                ),
+                Program {
+                    instructions: vec![
+                        Instruction::Int(1),
+                        Instruction::Int(2),
+                        Instruction::Binary(op),
+                    ],
+                    spans: vec![Span::new(0, 1), Span::new(4, 5), Span::new(0, 5)],
+                    stack_size: 2,
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn compiles_complex_expressions() {
+        // 1 + 2 * (3 + 4)
+        assert_compiles!(
+            binary(
+                Span::new(0, 15),
+                BinaryOp::Add,
+                int(Span::new(0, 1), 1),
+                binary(
+                    Span::new(4, 15),
+                    BinaryOp::Mul,
+                    int(Span::new(4, 5), 2),
+                    group(
+                        Span::new(8, 15),
+                        binary(
+                            Span::new(9, 14),
+                            BinaryOp::Add,
+                            int(Span::new(9, 10), 3),
+                            int(Span::new(13, 14), 4),
+                        ),
+                    ),
+                ),
+            ),
+            Program {
+                instructions: vec![
+                    Instruction::Int(1),
+                    Instruction::Int(2),
+                    Instruction::Int(3),
+                    Instruction::Int(4),
+                    Instruction::Binary(BinaryOp::Add),
+                    Instruction::Binary(BinaryOp::Mul),
+                    Instruction::Binary(BinaryOp::Add),
+                ],
+                spans: vec![
+                    Span::new(0, 1),
+                    Span::new(4, 5),
+                    Span::new(9, 10),
+                    Span::new(13, 14),
+                    Span::new(9, 14),
+                    Span::new(4, 15),
+                    Span::new(0, 15),
+                ],
+                stack_size: 4,
+            },
+        );
+    }
+}
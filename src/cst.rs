@@ -0,0 +1,274 @@
+//! Concrete syntax tree.
+//!
+//! Unlike the [abstract syntax tree](crate::ast), the concrete syntax tree
+//! keeps all tokens of the input, including parentheses and trivia
+//! (whitespace, newlines, and comments). Converting it back to a string using
+//! [`Display`](fmt::Display) reproduces the input exactly.
+//!
+//! The tree is stored in a [`Cst`] arena that mirrors the [`Ast`] of the same
+//! expression. Each [`CstNode`] has the same [`ExprId`] as the corresponding
+//! AST expression.
+//!
+//! # Examples
+//!
+//! ```
+//! use sari::TokenKind;
+//! use sari::cst::CstNode;
+//!
+//! let cst = sari::parse_cst("1 +  2\n")?;
+//!
+//! let CstNode::Binary { op, .. } = &cst[cst.root()] else {
+//!     panic!("expected a binary expression");
+//! };
+//! assert_eq!(op.kind(), TokenKind::Plus);
+//! assert_eq!(op.trailing_trivia()[0].text(), "  ");
+//!
+//! assert_eq!(cst.to_string(), "1 +  2\n");
+//! # Ok::<(), sari::Error>(())
+//! ```
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Index;
+
+use crate::ast::{Ast, ExprId};
+use crate::source::{Span, Spanned};
+use crate::token::TokenKind;
+
+/// Trivia, i.e. a piece of input that doesn't affect the meaning of an
+/// expression.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Trivia {
+    kind: TokenKind,
+    span: Span,
+    text: String,
+}
+
+impl Trivia {
+    pub(crate) fn new(kind: TokenKind, span: Span, text: String) -> Trivia {
+        Trivia { kind, span, text }
+    }
+
+    /// Returns the kind.
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    /// Returns the span.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns the text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Token in a concrete syntax tree, together with its trivia.
+///
+/// Trivia following a token on the same line is its trailing trivia. All other
+/// trivia is leading trivia of the next token.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SyntaxToken {
+    kind: TokenKind,
+    span: Span,
+    text: String,
+    leading_trivia: Vec<Trivia>,
+    trailing_trivia: Vec<Trivia>,
+}
+
+impl SyntaxToken {
+    pub(crate) fn new(kind: TokenKind, span: Span, text: String) -> SyntaxToken {
+        SyntaxToken {
+            kind,
+            span,
+            text,
+            leading_trivia: vec![],
+            trailing_trivia: vec![],
+        }
+    }
+
+    pub(crate) fn with_leading_trivia(mut self, trivia: Vec<Trivia>) -> SyntaxToken {
+        self.leading_trivia = trivia;
+        self
+    }
+
+    pub(crate) fn push_trailing_trivia(&mut self, trivia: Trivia) {
+        self.trailing_trivia.push(trivia);
+    }
+
+    /// Returns the kind.
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    /// Returns the span, excluding trivia.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Returns the text, excluding trivia.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the trivia preceding the token.
+    pub fn leading_trivia(&self) -> &[Trivia] {
+        &self.leading_trivia
+    }
+
+    /// Returns the trivia following the token on the same line.
+    pub fn trailing_trivia(&self) -> &[Trivia] {
+        &self.trailing_trivia
+    }
+}
+
+impl Spanned for SyntaxToken {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading_trivia {
+            f.write_str(&trivia.text)?;
+        }
+        f.write_str(&self.text)?;
+        for trivia in &self.trailing_trivia {
+            f.write_str(&trivia.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Node of a concrete syntax tree.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum CstNode {
+    /// Integer literal.
+    Int {
+        /// Literal token.
+        int: SyntaxToken,
+    },
+    /// Parenthesized expression.
+    Group {
+        /// `(` token.
+        l_paren: SyntaxToken,
+        /// Expression inside the parentheses.
+        expr: ExprId,
+        /// `)` token.
+        r_paren: SyntaxToken,
+    },
+    /// Binary expression.
+    Binary {
+        /// Left operand.
+        left: ExprId,
+        /// Operator token.
+        op: SyntaxToken,
+        /// Right operand.
+        right: ExprId,
+    },
+}
+
+/// Arena holding nodes of a concrete syntax tree.
+///
+/// A `Cst` is created by [`sari::parse_cst`](crate::parse_cst). Nodes are
+/// accessed by indexing the arena with their [`ExprId`], which is the same as
+/// the id of the corresponding expression in [`ast`](Cst::ast).
+///
+/// See the [module documentation](self) for details.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Cst {
+    ast: Ast,
+    nodes: Vec<CstNode>,
+    eof: SyntaxToken,
+}
+
+impl Cst {
+    pub(crate) fn new(ast: Ast, nodes: Vec<CstNode>, eof: SyntaxToken) -> Cst {
+        debug_assert!(!ast.is_empty());
+        debug_assert_eq!(ast.len(), nodes.len());
+
+        Cst { ast, nodes, eof }
+    }
+
+    /// Returns the abstract syntax tree of the expression.
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Returns the root node.
+    ///
+    /// Unlike an [`Ast`], a `Cst` is only created by parsing an expression, so
+    /// it is never empty.
+    pub fn root(&self) -> ExprId {
+        self.ast.root()
+    }
+
+    /// Returns the end of input token.
+    ///
+    /// The token has no text, but it holds the trivia at the end of the input.
+    pub fn eof(&self) -> &SyntaxToken {
+        &self.eof
+    }
+
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>, id: ExprId) -> fmt::Result {
+        enum Piece<'a> {
+            Node(ExprId),
+            Token(&'a SyntaxToken),
+        }
+
+        // Pieces are printed from the top of the stack, so the ones printed
+        // later are pushed first.
+        let mut stack = vec![Piece::Node(id)];
+
+        while let Some(piece) = stack.pop() {
+            let id = match piece {
+                Piece::Node(id) => id,
+                Piece::Token(token) => {
+                    write!(f, "{token}")?;
+                    continue;
+                }
+            };
+
+            match &self[id] {
+                CstNode::Int { int } => write!(f, "{int}")?,
+                CstNode::Group {
+                    l_paren,
+                    expr,
+                    r_paren,
+                } => {
+                    stack.push(Piece::Token(r_paren));
+                    stack.push(Piece::Node(*expr));
+                    stack.push(Piece::Token(l_paren));
+                }
+                CstNode::Binary { left, op, right } => {
+                    stack.push(Piece::Node(*right));
+                    stack.push(Piece::Token(op));
+                    stack.push(Piece::Node(*left));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Index<ExprId> for Cst {
+    type Output = CstNode;
+
+    fn index(&self, id: ExprId) -> &CstNode {
+        &self.nodes[id.index()]
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_node(f, self.root())?;
+        write!(f, "{}", self.eof)
+    }
+}
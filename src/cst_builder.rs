@@ -0,0 +1,305 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+use core::str::Chars;
+
+use crate::ast::{Ast, Expr, ExprId};
+use crate::cst::{Cst, CstNode, SyntaxToken, Trivia};
+use crate::limits::Limits;
+use crate::scanner::Scanner;
+use crate::source::{SourceMap, Spanned};
+use crate::token::TokenKind;
+
+// Builds a concrete syntax tree from an already parsed AST. The input is
+// scanned again with trivia, and the tokens are attached to the AST expressions
+// based on their spans.
+pub struct CstBuilder {
+    ast: Ast,
+    // Non-trivia tokens in input order, taken out as they are attached.
+    tokens: Vec<Option<SyntaxToken>>,
+    starts: Vec<usize>,
+}
+
+impl CstBuilder {
+    pub fn new(input: &str, ast: Ast) -> CstBuilder {
+        let tokens = scan_tokens(input);
+        let starts = tokens.iter().map(|token| token.span().start()).collect();
+
+        CstBuilder {
+            ast,
+            tokens: tokens.into_iter().map(Some).collect(),
+            starts,
+        }
+    }
+
+    pub fn build(mut self) -> Cst {
+        // Children precede their parents in the arena, so the nodes are built
+        // in the same order as the expressions and get the same ids.
+        let nodes = self
+            .ast
+            .ids()
+            .map(|id| self.build_node(id))
+            .collect::<Vec<_>>();
+        let eof = self
+            .tokens
+            .pop()
+            .flatten()
+            .expect("missing end of input token");
+
+        Cst::new(self.ast, nodes, eof)
+    }
+
+    fn build_node(&mut self, id: ExprId) -> CstNode {
+        match self.ast[id].clone() {
+            Expr::Int(expr) => CstNode::Int {
+                int: self.take_token(expr.span.start()),
+            },
+            Expr::Group(expr) => CstNode::Group {
+                l_paren: self.take_token(expr.span.start()),
+                expr: expr.expr,
+                r_paren: self.take_token(expr.span.end() - 1),
+            },
+            Expr::Binary(expr) => {
+                // Only trivia can separate the left operand and the operator.
+                let op_start = self.ast[expr.left].span().end();
+
+                CstNode::Binary {
+                    left: expr.left,
+                    op: self.take_token(op_start),
+                    right: expr.right,
+                }
+            }
+        }
+    }
+
+    // Takes the first token starting at or after `pos`.
+    fn take_token(&mut self, pos: usize) -> SyntaxToken {
+        let index = self.starts.partition_point(|&start| start < pos);
+
+        self.tokens[index].take().expect("token attached twice")
+    }
+}
+
+fn scan_tokens(input: &str) -> Vec<SyntaxToken> {
+    let mut scanner = Scanner::new(input, SourceMap::new(), Limits::default()).keep_trivia();
+
+    // Tokens cover the input without gaps, so their text can be taken from
+    // the input sequentially.
+    let mut chars = input.chars();
+
+    let mut tokens: Vec<SyntaxToken> = vec![];
+    let mut leading_trivia = vec![];
+    let mut in_trailing_trivia = false;
+
+    loop {
+        let token = scanner.scan_token();
+        let span = token.span();
+        let text = take_text(&mut chars, span.end() - span.start());
+
+        if token.kind().is_trivia() {
+            let trivia = Trivia::new(token.kind(), span, text);
+
+            if token.kind() == TokenKind::Newline {
+                in_trailing_trivia = false;
+            }
+
+            match tokens.last_mut() {
+                Some(last) if in_trailing_trivia => last.push_trailing_trivia(trivia),
+                _ => leading_trivia.push(trivia),
+            }
+        } else {
+            let trivia = mem::take(&mut leading_trivia);
+            tokens.push(SyntaxToken::new(token.kind(), span, text).with_leading_trivia(trivia));
+            in_trailing_trivia = true;
+
+            if token.kind() == TokenKind::Eof {
+                return tokens;
+            }
+        }
+    }
+}
+
+fn take_text(chars: &mut Chars, len: usize) -> String {
+    chars.by_ref().take(len).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::parser::Parser;
+    use crate::source::Span;
+
+    fn build(input: &str) -> Cst {
+        let ast = Parser::new(input, SourceMap::new(), Limits::default())
+            .parse()
+            .unwrap();
+
+        CstBuilder::new(input, ast).build()
+    }
+
+    #[test]
+    fn builds_int_node() {
+        let cst = build(" 1 ");
+
+        let CstNode::Int { int } = &cst[cst.root()] else {
+            panic!("expected an integer literal");
+        };
+        assert_eq!(int.kind(), TokenKind::Int);
+        assert_eq!(int.span(), Span::new(1, 2));
+        assert_eq!(int.text(), "1");
+        assert_eq!(
+            int.leading_trivia(),
+            [Trivia::new(
+                TokenKind::Whitespace,
+                Span::new(0, 1),
+                " ".to_string()
+            )],
+        );
+        assert_eq!(
+            int.trailing_trivia(),
+            [Trivia::new(
+                TokenKind::Whitespace,
+                Span::new(2, 3),
+                " ".to_string()
+            )],
+        );
+    }
+
+    #[test]
+    fn builds_group_node() {
+        let cst = build("( 1 )");
+
+        let CstNode::Group {
+            l_paren,
+            expr,
+            r_paren,
+        } = &cst[cst.root()]
+        else {
+            panic!("expected a parenthesized expression");
+        };
+        assert_eq!(l_paren.text(), "(");
+        assert_eq!(r_paren.text(), ")");
+        assert!(matches!(cst[*expr], CstNode::Int { .. }));
+    }
+
+    #[test]
+    fn builds_binary_node() {
+        let cst = build("1\n+ 2");
+
+        let CstNode::Binary { left, op, right } = &cst[cst.root()] else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(op.text(), "+");
+        assert_eq!(
+            op.leading_trivia(),
+            [Trivia::new(
+                TokenKind::Newline,
+                Span::new(1, 2),
+                "\n".to_string()
+            )],
+        );
+        assert!(matches!(cst[*left], CstNode::Int { .. }));
+        assert!(matches!(cst[*right], CstNode::Int { .. }));
+    }
+
+    #[test]
+    fn attaches_trivia() {
+        let cst = build("1 \n \n+ 2 \n");
+
+        let CstNode::Binary { left, op, right } = &cst[cst.root()] else {
+            panic!("expected a binary expression");
+        };
+        let CstNode::Int { int: left } = &cst[*left] else {
+            panic!("expected an integer literal");
+        };
+        let CstNode::Int { int: right } = &cst[*right] else {
+            panic!("expected an integer literal");
+        };
+
+        // trivia on the same line is trailing
+        assert_eq!(left.leading_trivia(), []);
+        assert_eq!(
+            left.trailing_trivia(),
+            [Trivia::new(
+                TokenKind::Whitespace,
+                Span::new(1, 2),
+                " ".to_string()
+            )],
+        );
+
+        // trivia on following lines is leading
+        assert_eq!(
+            op.leading_trivia(),
+            [
+                Trivia::new(TokenKind::Newline, Span::new(2, 3), "\n".to_string()),
+                Trivia::new(TokenKind::Whitespace, Span::new(3, 4), " ".to_string()),
+                Trivia::new(TokenKind::Newline, Span::new(4, 5), "\n".to_string()),
+            ],
+        );
+        assert_eq!(
+            right.trailing_trivia(),
+            [Trivia::new(
+                TokenKind::Whitespace,
+                Span::new(8, 9),
+                " ".to_string()
+            )],
+        );
+
+        // trivia at the end belongs to the end of input
+        assert_eq!(cst.eof().kind(), TokenKind::Eof);
+        assert_eq!(
+            cst.eof().leading_trivia(),
+            [Trivia::new(
+                TokenKind::Newline,
+                Span::new(9, 10),
+                "\n".to_string()
+            )],
+        );
+    }
+
+    #[test]
+    fn attaches_comments() {
+        let cst = build("// a\n1 /* b */\n");
+
+        let CstNode::Int { int } = &cst[cst.root()] else {
+            panic!("expected an integer literal");
+        };
+        assert_eq!(
+            int.leading_trivia(),
+            [
+                Trivia::new(TokenKind::LineComment, Span::new(0, 4), "// a".to_string()),
+                Trivia::new(TokenKind::Newline, Span::new(4, 5), "\n".to_string()),
+            ],
+        );
+        assert_eq!(
+            int.trailing_trivia(),
+            [
+                Trivia::new(TokenKind::Whitespace, Span::new(6, 7), " ".to_string()),
+                Trivia::new(
+                    TokenKind::BlockComment,
+                    Span::new(7, 14),
+                    "/* b */".to_string()
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn round_trips_input() {
+        for input in [
+            "1",
+            "  1  ",
+            "1+2*3",
+            "  ( 1 +\t2 )\r\n*\n\n 3 \n",
+            "((1))",
+            "1 - (2 / (3 * 4)) + 5",
+            "\n\n1\n\n",
+            "// a\n1 /* b */ + /* c\n*/ 2 // d",
+        ] {
+            assert_eq!(build(input).to_string(), input);
+        }
+    }
+}
@@ -0,0 +1,401 @@
+// Documents describing how to lay out text, printed by `Printer`. This is a
+// simplified version of Wadler's "prettier printer": a group is printed on one
+// line if it fits within the width, otherwise its line breaks are used.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const INDENT: usize = 4;
+
+pub enum Doc {
+    Text(String),
+    // Space if the enclosing group fits on one line, line break otherwise.
+    Line,
+    // Nothing if the enclosing group fits on one line, line break otherwise.
+    SoftLine,
+    // Text printed after a space at the end of the current line, used for line
+    // comments following a token. Any text printed after it goes on the next
+    // line.
+    LineSuffix(String),
+    // Text printed on its own line before the next text, used for line
+    // comments preceding a token.
+    LinePrefix(String),
+    // Text printed before the next text, used for block comments that have to
+    // stay next to the following token.
+    Prefix(String),
+    Concat(Vec<Doc>),
+    Indent(Vec<Doc>),
+    Group(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+type Command<'a> = (usize, Mode, &'a Doc);
+
+// Text waiting to be printed before the next text.
+#[derive(Clone)]
+enum Prefix {
+    Line(String),
+    Inline(String),
+}
+
+pub struct Printer {
+    width: Option<usize>,
+    output: String,
+    column: usize,
+    // Whether the current line has any text other than spaces, and whether
+    // some of it isn't a comment.
+    line_has_text: bool,
+    line_has_code: bool,
+    // Widest line so far, not counting lines with only comments.
+    max_column: usize,
+    line_suffixes: Vec<String>,
+    prefixes: Vec<Prefix>,
+}
+
+impl Printer {
+    pub fn new(width: Option<usize>) -> Printer {
+        Printer {
+            width,
+            output: String::new(),
+            column: 0,
+            line_has_text: false,
+            line_has_code: false,
+            max_column: 0,
+            line_suffixes: vec![],
+            prefixes: vec![],
+        }
+    }
+
+    pub fn print(mut self, doc: &Doc) -> String {
+        let mut commands: Vec<Command> = vec![(0, Mode::Break, doc)];
+
+        while let Some((indent, mode, doc)) = commands.pop() {
+            match doc {
+                Doc::Concat(docs) => {
+                    commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                Doc::Indent(docs) => {
+                    commands.extend(docs.iter().rev().map(|doc| (indent + INDENT, mode, doc)));
+                }
+                Doc::Group(docs) => {
+                    let mode = if mode == Mode::Flat || self.fits(docs, indent, &commands) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+
+                    commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                _ => self.print_leaf(indent, mode, doc),
+            }
+        }
+
+        self.finish();
+
+        let len = self.output.trim_end().len();
+        self.output.truncate(len);
+
+        self.output
+    }
+
+    // Writes the line suffixes and prefixes pending at the end of the document.
+    fn finish(&mut self) {
+        if !self.line_suffixes.is_empty() && !self.prefixes.is_empty() {
+            self.newline(0);
+        }
+        self.flush_line_suffixes();
+        self.flush_prefixes(0);
+    }
+
+    fn print_leaf(&mut self, indent: usize, mode: Mode, doc: &Doc) {
+        match doc {
+            Doc::Text(text) => self.text(text, indent),
+            Doc::Line if mode == Mode::Flat => self.text(" ", indent),
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine => self.newline(indent),
+            // A line holds only one line suffix, otherwise they would read as a
+            // single comment. Line suffixes also can't jump ahead of prefixes.
+            // In both cases, they go on their own line.
+            Doc::LineSuffix(text)
+                if !self.line_suffixes.is_empty() || !self.prefixes.is_empty() =>
+            {
+                self.prefixes.push(Prefix::Line(text.clone()));
+            }
+            Doc::LineSuffix(text) => self.line_suffixes.push(text.clone()),
+            Doc::LinePrefix(text) => self.prefixes.push(Prefix::Line(text.clone())),
+            Doc::Prefix(text) => self.prefixes.push(Prefix::Inline(text.clone())),
+            Doc::Concat(_) | Doc::Indent(_) | Doc::Group(_) => unreachable!(),
+        }
+    }
+
+    // Checks whether `docs` printed on one line, followed by the rest of the
+    // current line, fit within the width. They are printed the same way as in
+    // `print`, so that the layout depends only on the printed text and not on
+    // where comments are attached in the document. Lines forced by comments
+    // must fit as well, but the comments themselves aren't measured.
+    fn fits(&self, docs: &[Doc], indent: usize, rest: &[Command]) -> bool {
+        let Some(width) = self.width else {
+            return true;
+        };
+
+        // Printing looks back only at spaces at the end of the output, so the
+        // copy doesn't need the rest.
+        let spaces = self.output.len() - self.output.trim_end_matches(' ').len();
+        let mut printer = Printer {
+            width: self.width,
+            output: self.output[self.output.len() - spaces..].into(),
+            column: self.column,
+            line_has_text: self.line_has_text,
+            line_has_code: self.line_has_code,
+            max_column: self.column,
+            line_suffixes: self.line_suffixes.clone(),
+            prefixes: self.prefixes.clone(),
+        };
+        let mut commands: Vec<Command> = docs
+            .iter()
+            .rev()
+            .map(|doc| (indent, Mode::Flat, doc))
+            .collect();
+        let mut rest = rest.iter().rev();
+
+        while let Some((indent, mode, doc)) = commands.pop().or_else(|| rest.next().copied()) {
+            match doc {
+                Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+                Doc::Concat(docs) | Doc::Group(docs) => {
+                    commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                Doc::Indent(docs) => {
+                    commands.extend(docs.iter().rev().map(|doc| (indent + INDENT, mode, doc)));
+                }
+                _ => {
+                    printer.print_leaf(indent, mode, doc);
+
+                    if printer.max_column > width {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        printer.finish();
+
+        printer.max_column <= width
+    }
+
+    // Writes text, preceded by the pending line suffixes and prefixes.
+    // Whitespace is dropped at the start of a line, after another space, and
+    // before pending line suffixes and prefixes, which are followed by a line
+    // break.
+    fn text(&mut self, text: &str, indent: usize) {
+        if text.trim().is_empty() {
+            if self.line_suffixes.is_empty()
+                && self.prefixes.is_empty()
+                && !self.at_line_start()
+                && !self.output.ends_with(' ')
+            {
+                self.write_measured(text);
+            }
+
+            return;
+        }
+
+        if !self.line_suffixes.is_empty() {
+            self.newline(indent);
+        }
+        self.flush_prefixes(indent);
+
+        self.write_measured(text);
+        self.line_has_code = true;
+    }
+
+    fn flush_prefixes(&mut self, indent: usize) {
+        for prefix in core::mem::take(&mut self.prefixes) {
+            match prefix {
+                Prefix::Line(text) => {
+                    if !self.at_line_start() {
+                        self.newline(indent);
+                    }
+                    self.write(&text);
+                    self.newline(indent);
+                }
+                Prefix::Inline(text) => {
+                    if !self.at_line_start() && !self.output.ends_with(' ') {
+                        self.write(" ");
+                    }
+                    // Lines with only comments aren't measured. If code
+                    // follows on the line, it is measured together with the
+                    // comment.
+                    if self.line_has_code {
+                        self.write_measured(&text);
+                    } else {
+                        self.write(&text);
+                    }
+                    self.write(" ");
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+        self.column += text.chars().count();
+        self.line_has_text |= !text.trim().is_empty();
+    }
+
+    // Writes text which counts towards the width of the line.
+    fn write_measured(&mut self, text: &str) {
+        self.write(text);
+        self.max_column = self.max_column.max(self.column);
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.flush_line_suffixes();
+
+        self.trim_end();
+        self.output.push('\n');
+        self.output.extend(core::iter::repeat_n(' ', indent));
+        self.column = indent;
+        self.line_has_text = false;
+        self.line_has_code = false;
+    }
+
+    fn flush_line_suffixes(&mut self) {
+        for text in core::mem::take(&mut self.line_suffixes) {
+            self.trim_end();
+            self.write(" ");
+            self.write(&text);
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        !self.line_has_text
+    }
+
+    // Removes spaces at the end of the current line.
+    fn trim_end(&mut self) {
+        let len = self.output.trim_end_matches(' ').len();
+        self.column -= self.output.len() - len;
+        self.output.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
+
+    use super::*;
+
+    fn chain(operands: &[&str]) -> Doc {
+        let mut rest = vec![];
+        for operand in &operands[1..] {
+            rest.push(Doc::Line);
+            rest.push(Doc::text(format!("+ {operand}")));
+        }
+
+        Doc::Group(vec![Doc::text(operands[0]), Doc::Indent(rest)])
+    }
+
+    #[test]
+    fn prints_text() {
+        let doc = Doc::Concat(vec![Doc::text("a"), Doc::text("b")]);
+
+        assert_eq!(Printer::new(None).print(&doc), "ab");
+    }
+
+    #[test]
+    fn prints_groups_that_fit_flat() {
+        let doc = chain(&["1", "2", "3"]);
+
+        assert_eq!(Printer::new(None).print(&doc), "1 + 2 + 3");
+        assert_eq!(Printer::new(Some(9)).print(&doc), "1 + 2 + 3");
+    }
+
+    #[test]
+    fn breaks_groups_that_do_not_fit() {
+        let doc = chain(&["1", "2", "3"]);
+
+        assert_eq!(Printer::new(Some(8)).print(&doc), "1\n    + 2\n    + 3");
+    }
+
+    #[test]
+    fn prints_line_suffixes_at_end_of_line() {
+        let doc = Doc::Group(vec![
+            Doc::text("1"),
+            Doc::LineSuffix("// a".to_string()),
+            Doc::Line,
+            Doc::text("2"),
+        ]);
+
+        assert_eq!(Printer::new(None).print(&doc), "1 // a\n2");
+    }
+
+    #[test]
+    fn breaks_lines_after_line_suffixes() {
+        let doc = Doc::Group(vec![
+            Doc::text("1"),
+            Doc::Line,
+            Doc::text("+"),
+            Doc::LineSuffix("// a".to_string()),
+            Doc::text(" "),
+            Doc::text("2"),
+            Doc::LineSuffix("// b".to_string()),
+            Doc::LineSuffix("// c".to_string()),
+        ]);
+
+        assert_eq!(Printer::new(None).print(&doc), "1 + // a\n2 // b\n// c");
+    }
+
+    #[test]
+    fn prints_line_prefixes_on_their_own_lines() {
+        let doc = Doc::Group(vec![
+            Doc::text("1"),
+            Doc::Line,
+            Doc::text("+"),
+            Doc::text(" "),
+            Doc::LinePrefix("// a".to_string()),
+            Doc::text("2"),
+            Doc::LinePrefix("// b".to_string()),
+        ]);
+
+        assert_eq!(Printer::new(None).print(&doc), "1 +\n// a\n2\n// b");
+    }
+
+    #[test]
+    fn prints_prefixes_before_next_text() {
+        let doc = Doc::Group(vec![
+            Doc::text("1"),
+            Doc::Prefix("/* a */".to_string()),
+            Doc::Line,
+            Doc::text("+ 2"),
+        ]);
+
+        assert_eq!(Printer::new(None).print(&doc), "1 /* a */ + 2");
+        assert_eq!(Printer::new(Some(1)).print(&doc), "1\n/* a */ + 2");
+    }
+
+    #[test]
+    fn soft_lines_disappear_in_flat_groups() {
+        let doc = Doc::Group(vec![
+            Doc::text("("),
+            Doc::Indent(vec![Doc::SoftLine, Doc::text("1")]),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]);
+
+        assert_eq!(Printer::new(None).print(&doc), "(1)");
+        assert_eq!(Printer::new(Some(2)).print(&doc), "(\n    1\n)");
+    }
+}
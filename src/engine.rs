@@ -0,0 +1,249 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use crate::cst::Cst;
+use crate::cst_builder::CstBuilder;
+use crate::error::Error;
+use crate::expression::Expression;
+use crate::format_options::FormatOptions;
+use crate::formatter::Formatter;
+use crate::limits::Limits;
+use crate::parser::Parser;
+use crate::session::Session;
+use crate::source::SourceMap;
+use crate::source_db::{SourceDb, SourceId};
+use crate::trace::Trace;
+
+/// Configured evaluator of expressions.
+///
+/// An engine is configured once and then used to parse, evaluate, and format
+/// any number of expressions. It is cheap to copy and it is `Send` and `Sync`,
+/// so it can be shared between threads. The expressions it produces, including
+/// their compiled forms, are `Send` and `Sync` too.
+///
+/// The engine holds only its configuration, so each call allocates its own
+/// buffers. To reuse them when evaluating many expressions, create a
+/// [`Session`] for each thread using [`session`](Engine::session).
+///
+/// The functions at the crate root, like [`sari::eval`](crate::eval), and the
+/// methods of [`SourceDb`] use an engine with the default configuration.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use sari::{Engine, Limit, Limits};
+///
+/// let engine = Engine::new().with_limits(Limits {
+///     max_tokens: 7,
+///     ..Limits::default()
+/// });
+///
+/// thread::scope(|scope| {
+///     let a = scope.spawn(|| engine.eval("(1 + 2) * 3"));
+///     let b = scope.spawn(|| engine.eval("1 + 2 + 3 + 4 + 5"));
+///
+///     assert_eq!(a.join().unwrap(), Ok(9));
+///     assert_eq!(b.join().unwrap().unwrap_err().limit(), Some(Limit::Tokens));
+/// });
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Engine {
+    limits: Limits,
+    format_options: FormatOptions,
+}
+
+impl Engine {
+    /// Creates a new `Engine` with the default configuration.
+    pub fn new() -> Engine {
+        Engine::default()
+    }
+
+    /// Returns the engine with specified limits.
+    ///
+    /// The limits apply to parsing and to evaluating the parsed expressions.
+    /// See [`Limits`] for details.
+    pub fn with_limits(mut self, limits: Limits) -> Engine {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the engine with specified formatting options.
+    ///
+    /// See [`FormatOptions`] for details.
+    pub fn with_format_options(mut self, format_options: FormatOptions) -> Engine {
+        self.format_options = format_options;
+        self
+    }
+
+    /// Returns the limits.
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Returns the formatting options.
+    pub fn format_options(&self) -> FormatOptions {
+        self.format_options
+    }
+
+    /// Creates a session for evaluating many expressions with the engine,
+    /// reusing memory between them.
+    ///
+    /// See [`Session`] for details.
+    pub fn session(&self) -> Session {
+        Session::new(*self)
+    }
+
+    /// Evaluates an expression and returns the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the evaluation fails or a limit is exceeded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::Engine;
+    ///
+    /// let engine = Engine::new();
+    ///
+    /// assert_eq!(engine.eval("(1 + 2) * 3"), Ok(9));
+    /// assert_eq!(engine.eval("1 / 0").unwrap_err().message(), "division by zero");
+    /// ```
+    pub fn eval(&self, expr: &str) -> Result<i32, Error> {
+        self.parse(expr)?.eval()
+    }
+
+    /// Evaluates an expression step by step and returns a trace of the
+    /// evaluation.
+    ///
+    /// See [`Trace`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails or a limit is exceeded. Errors of
+    /// the evaluation itself are reported by [`Trace::result`].
+    pub fn eval_traced(&self, expr: &str) -> Result<Trace, Error> {
+        Ok(self.parse(expr)?.eval_traced())
+    }
+
+    /// Parses an expression and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails or a limit is exceeded.
+    pub fn parse(&self, expr: &str) -> Result<Expression, Error> {
+        self.parse_with_source_map(expr, SourceMap::new())
+    }
+
+    /// Parses a source from a [`SourceDb`] and returns the expression.
+    ///
+    /// See [`SourceDb::parse`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails or a limit is exceeded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` doesn't identify a source in the database.
+    pub fn parse_source(&self, db: &SourceDb, id: SourceId) -> Result<Expression, Error> {
+        let source_map = SourceMap::with_source(id, db.name(id));
+
+        self.parse_with_source_map(db.text(id), source_map)
+    }
+
+    /// Parses an expression and returns its concrete syntax tree.
+    ///
+    /// See [`sari::parse_cst`](crate::parse_cst) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails or a limit is exceeded.
+    pub fn parse_cst(&self, expr: &str) -> Result<Cst, Error> {
+        let ast = Parser::new(expr, SourceMap::new(), self.limits).parse()?;
+
+        Ok(CstBuilder::new(expr, ast).build())
+    }
+
+    /// Formats an expression using the formatting options of the engine.
+    ///
+    /// See [`sari::format`](crate::format()) for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails or a limit is exceeded.
+    pub fn format(&self, expr: &str) -> Result<String, Error> {
+        let cst = self.parse_cst(expr)?;
+
+        Ok(Formatter::new(&cst, self.format_options).format())
+    }
+
+    fn parse_with_source_map(
+        &self,
+        expr: &str,
+        source_map: SourceMap,
+    ) -> Result<Expression, Error> {
+        let (ast, source_map) =
+            Parser::new(expr, source_map, self.limits).parse_with_source_map()?;
+
+        Ok(Expression::new(ast, Arc::new(source_map), self.limits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::bytecode::Bytecode;
+    use crate::compiled_expression::CompiledExpression;
+    use crate::limits::Limit;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn is_send_and_sync() {
+        assert_send_sync::<Engine>();
+        assert_send_sync::<Session>();
+        assert_send_sync::<Expression>();
+        assert_send_sync::<Bytecode>();
+        assert_send_sync::<CompiledExpression>();
+        assert_send_sync::<Trace>();
+        assert_send_sync::<Error>();
+    }
+
+    #[test]
+    fn applies_limits() {
+        let engine = Engine::new().with_limits(Limits {
+            max_steps: 3,
+            ..Limits::default()
+        });
+
+        assert_eq!(engine.eval("1 + 2"), Ok(3));
+        assert_eq!(
+            engine.eval("(1 + 2)").unwrap_err().limit(),
+            Some(Limit::Steps),
+        );
+
+        let mut db = SourceDb::new();
+        let id = db.add("a.sari", "(1 + 2)");
+        let error = engine.parse_source(&db, id).unwrap().eval().unwrap_err();
+        assert_eq!(error.limit(), Some(Limit::Steps));
+        assert_eq!(error.source_id(), Some(id));
+
+        // formatting doesn't evaluate
+        assert_eq!(engine.format("(1 + 2)"), Ok("1 + 2".to_string()));
+    }
+
+    #[test]
+    fn applies_format_options() {
+        let engine = Engine::new().with_format_options(FormatOptions { max_width: Some(8) });
+
+        assert_eq!(
+            engine.format("1+2+3"),
+            Ok("1\n    + 2\n    + 3".to_string())
+        );
+    }
+}
@@ -1,9 +1,15 @@
-use std::{error, fmt};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use core::{error, fmt};
 
-use crate::SourceSpan;
+use crate::{Limit, SourceId, SourceSpan};
 
 /// Error returned when expression evaluation fails.
 ///
+/// Errors of expressions from a [`SourceDb`](crate::SourceDb) also refer to
+/// the source they occurred in, and their [`Display`](fmt::Display)
+/// implementation prefixes the span with the source name.
+///
 /// # Examples
 ///
 /// ```
@@ -20,9 +26,25 @@ use crate::SourceSpan;
 /// assert_eq!(result, Err(error));
 /// ```
 #[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Error {
     span: SourceSpan,
     message: String,
+    limit: Option<Limit>,
+    // The source is boxed to keep errors small, as they are returned through
+    // deeply recursive parsing and evaluation.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    source: Option<Box<ErrorSource>>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ErrorSource {
+    id: SourceId,
+    name: String,
 }
 
 impl Error {
@@ -46,9 +68,64 @@ impl Error {
         Error {
             span,
             message: message.into(),
+            limit: None,
+            source: None,
+        }
+    }
+
+    /// Creates a new `Error` reporting that specified limit was exceeded.
+    ///
+    /// The message is determined by the limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::{Error, Limit, SourcePos, SourceSpan};
+    ///
+    /// let span = SourceSpan::new(
+    ///     SourcePos::new(69, 5, 7), // offset 69, line 5, column 7
+    ///     SourcePos::new(74, 5, 12), // offset 74, line 5, column 12
+    /// );
+    /// let error = Error::limit_exceeded(span, Limit::Depth);
+    ///
+    /// assert_eq!(error.span(), span);
+    /// assert_eq!(error.message(), "expression nested too deeply");
+    /// assert_eq!(error.limit(), Some(Limit::Depth));
+    /// ```
+    pub fn limit_exceeded(span: SourceSpan, limit: Limit) -> Error {
+        Error {
+            span,
+            message: limit.message().to_string(),
+            limit: Some(limit),
+            source: None,
         }
     }
 
+    /// Returns the error with specified source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::{Error, SourceDb, SourcePos, SourceSpan};
+    ///
+    /// let mut db = SourceDb::new();
+    /// let id = db.add("pricing.sari", "1 / 0");
+    ///
+    /// let span = SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(5, 1, 6));
+    /// let error = Error::new(span, "division by zero").with_source(id, "pricing.sari");
+    ///
+    /// assert_eq!(error.source_id(), Some(id));
+    /// assert_eq!(error.source_name(), Some("pricing.sari"));
+    /// assert_eq!(error.to_string(), "pricing.sari:1:1-1:6: division by zero");
+    /// ```
+    pub fn with_source(mut self, id: SourceId, name: impl Into<String>) -> Error {
+        self.source = Some(Box::new(ErrorSource {
+            id,
+            name: name.into(),
+        }));
+        self
+    }
+
     /// Returns the span.
     pub fn span(&self) -> SourceSpan {
         self.span
@@ -58,10 +135,32 @@ impl Error {
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    /// Returns the limit that was exceeded, if the error was caused by
+    /// exceeding one of the [`Limits`](crate::Limits).
+    pub fn limit(&self) -> Option<Limit> {
+        self.limit
+    }
+
+    /// Returns the identifier of the source the error occurred in, if the
+    /// expression came from a [`SourceDb`](crate::SourceDb).
+    pub fn source_id(&self) -> Option<SourceId> {
+        self.source.as_ref().map(|source| source.id)
+    }
+
+    /// Returns the name of the source the error occurred in, if the expression
+    /// came from a [`SourceDb`](crate::SourceDb).
+    pub fn source_name(&self) -> Option<&str> {
+        self.source.as_ref().map(|source| source.name.as_str())
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(source) = &self.source {
+            write!(f, "{}:", source.name)?;
+        }
+
         write!(f, "{}: {}", &self.span, &self.message)
     }
 }
@@ -71,7 +170,7 @@ impl error::Error for Error {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::SourcePos;
+    use crate::{SourceDb, SourcePos};
 
     #[test]
     fn error_fmt_works() {
@@ -79,5 +178,11 @@ mod tests {
         let error = Error::new(span, "division by zero");
 
         assert_eq!(error.to_string(), "1:5-2:3: division by zero");
+
+        let mut db = SourceDb::new();
+        let id = db.add("pricing.sari", "");
+        let error = error.with_source(id, "pricing.sari");
+
+        assert_eq!(error.to_string(), "pricing.sari:1:5-2:3: division by zero");
     }
 }
@@ -1,78 +1,128 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
 
-use crate::ast::{BinaryExpr, BinaryOp, Expr, GroupExpr, IntExpr};
+use crate::ast::{Ast, BinaryExpr, Expr, ExprId, GroupExpr, IntExpr, Step};
 use crate::error::Error;
-use crate::source::{SourceMap, SourceSpan, Span, Spanned};
+use crate::limits::{Limit, Limits};
+use crate::source::{SourceMap, Spanned};
+use crate::trace::Reduction;
 
 pub struct Evaluator<'a> {
-    ast: &'a Expr,
-    source_map: Rc<RefCell<SourceMap>>,
+    ast: &'a Ast,
+    source_map: &'a SourceMap,
+    limits: Limits,
+    steps: usize,
+    reductions: Option<Vec<Reduction>>,
 }
 
-impl Evaluator<'_> {
-    pub fn new(ast: &Expr, source_map: Rc<RefCell<SourceMap>>) -> Evaluator {
-        Evaluator { ast, source_map }
+impl<'a> Evaluator<'a> {
+    pub fn new(ast: &'a Ast, source_map: &'a SourceMap, limits: Limits) -> Evaluator<'a> {
+        Evaluator {
+            ast,
+            source_map,
+            limits,
+            steps: 0,
+            reductions: None,
+        }
+    }
+
+    pub fn eval(mut self) -> Result<i32, Error> {
+        self.eval_expr(self.ast.root())
     }
 
-    pub fn eval(&self) -> Result<i32, Error> {
-        self.eval_expr(self.ast)
+    pub fn eval_traced(mut self) -> (Result<i32, Error>, Vec<Reduction>) {
+        self.reductions = Some(vec![]);
+        let result = self.eval_expr(self.ast.root());
+
+        (result, self.reductions.unwrap_or_default())
     }
 
-    fn eval_expr(&self, expr: &Expr) -> Result<i32, Error> {
-        match expr {
-            Expr::Int(expr) => self.eval_int_expr(expr),
-            Expr::Group(expr) => self.eval_group_expr(expr),
-            Expr::Binary(expr) => self.eval_binary_expr(expr),
+    fn eval_expr(&mut self, id: ExprId) -> Result<i32, Error> {
+        let ast = self.ast;
+        // Values of the expressions which are left, but whose parents aren't.
+        let mut values = vec![];
+
+        for step in ast.walk(id) {
+            match step {
+                Step::Enter(id) => {
+                    if self.steps >= self.limits.max_steps {
+                        return Err(self.source_map.limit_error(ast[id].span(), Limit::Steps));
+                    }
+                    self.steps += 1;
+                }
+                Step::Leave(id) => {
+                    let value = match &ast[id] {
+                        Expr::Int(expr) => self.eval_int_expr(expr)?,
+                        Expr::Group(expr) => {
+                            let inner = pop_value(&mut values);
+                            self.eval_group_expr(expr, inner)?
+                        }
+                        Expr::Binary(expr) => {
+                            let right = pop_value(&mut values);
+                            let left = pop_value(&mut values);
+                            self.eval_binary_expr(id, expr, left, right)?
+                        }
+                    };
+
+                    values.push(value);
+                }
+            }
         }
+
+        Ok(pop_value(&mut values))
     }
 
-    fn eval_int_expr(&self, expr: &IntExpr) -> Result<i32, Error> {
+    fn eval_int_expr(&mut self, expr: &IntExpr) -> Result<i32, Error> {
         Ok(expr.value)
     }
 
-    fn eval_group_expr(&self, expr: &GroupExpr) -> Result<i32, Error> {
-        self.eval_expr(&expr.expr)
+    fn eval_group_expr(&mut self, _expr: &GroupExpr, inner: i32) -> Result<i32, Error> {
+        Ok(inner)
     }
 
-    fn eval_binary_expr(&self, expr: &BinaryExpr) -> Result<i32, Error> {
-        let left = self.eval_expr(&expr.left)?;
-        let right = self.eval_expr(&expr.right)?;
-
-        match expr.op {
-            BinaryOp::Add => Ok(left.wrapping_add(right)),
-            BinaryOp::Sub => Ok(left.wrapping_sub(right)),
-            BinaryOp::Mul => Ok(left.wrapping_mul(right)),
-            BinaryOp::Div => {
-                if right == 0 {
-                    return Err(self.error(expr, "division by zero"));
-                }
+    fn eval_binary_expr(
+        &mut self,
+        id: ExprId,
+        expr: &BinaryExpr,
+        left: i32,
+        right: i32,
+    ) -> Result<i32, Error> {
+        let value = expr
+            .op
+            .apply(left, right)
+            .ok_or_else(|| self.error(expr, "division by zero"))?;
 
-                Ok(left.wrapping_div(right))
-            }
+        if let Some(reductions) = &mut self.reductions {
+            let span = self.source_map.map_span(expr.span);
+            reductions.push(Reduction::new(id, left, expr.op, right, value, span));
         }
+
+        Ok(value)
     }
 
     fn error(&self, spanned: &impl Spanned, message: impl Into<String>) -> Error {
-        Error::new(self.map_span(spanned.span()), message)
+        self.source_map.error(spanned.span(), message)
     }
+}
 
-    fn map_span(&self, span: Span) -> SourceSpan {
-        self.source_map.borrow().map_span(span)
-    }
+fn pop_value(values: &mut Vec<i32>) -> i32 {
+    values.pop().expect("child expressions are evaluated")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::BinaryOp;
+    use crate::ast::build::{ast, binary, group, int};
     use crate::source::{SourcePos, SourceSpan, Span};
 
     macro_rules! assert_evals {
         ($ast:expr, $value:expr $(,)?) => {
-            let source_map = Rc::new(RefCell::new(SourceMap::new()));
+            let source_map = SourceMap::new();
 
-            let ast = $ast;
-            let evaluator = Evaluator::new(&ast, Rc::clone(&source_map));
+            let ast = ast($ast);
+            let evaluator = Evaluator::new(&ast, &source_map, Limits::default());
 
             assert_eq!(evaluator.eval(), Ok($value));
         };
@@ -80,10 +130,10 @@ mod tests {
 
     macro_rules! assert_does_not_eval {
         ($ast:expr, $error:expr $(,)?) => {
-            let source_map = Rc::new(RefCell::new(SourceMap::new()));
+            let source_map = SourceMap::new();
 
-            let ast = $ast;
-            let evaluator = Evaluator::new(&ast, Rc::clone(&source_map));
+            let ast = ast($ast);
+            let evaluator = Evaluator::new(&ast, &source_map, Limits::default());
 
             assert_eq!(evaluator.eval(), Err($error));
         };
@@ -91,45 +141,42 @@ mod tests {
 
     #[test]
     fn evals_int_expr() {
-        assert_evals!(Expr::int(Span::new(0, 1), 1), 1);
+        assert_evals!(int(Span::new(0, 1), 1), 1);
     }
 
     #[test]
     fn evals_group_expr() {
-        assert_evals!(
-            Expr::group(Span::new(0, 3), Expr::int(Span::new(1, 2), 1)),
-            1,
-        );
+        assert_evals!(group(Span::new(0, 3), int(Span::new(1, 2), 1)), 1);
     }
 
     #[test]
     fn evals_binary_expr_add() {
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Add,
-                Expr::int(Span::new(0, 1), 1),
-                Expr::int(Span::new(4, 5), 2),
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
             ),
             3,
         );
 
         // overflow
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 14),
                 BinaryOp::Add,
-                Expr::int(Span::new(0, 10), 2147483647),
-                Expr::int(Span::new(13, 14), 1),
+                int(Span::new(0, 10), 2147483647),
+                int(Span::new(13, 14), 1),
             ),
             -2147483648,
         );
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 16),
                 BinaryOp::Add,
-                Expr::int(Span::new(0, 11), -2147483648),
-                Expr::int(Span::new(14, 16), -1),
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 16), -1),
             ),
             2147483647,
         );
@@ -138,31 +185,31 @@ mod tests {
     #[test]
     fn evals_binary_expr_sub() {
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Sub,
-                Expr::int(Span::new(0, 1), 3),
-                Expr::int(Span::new(4, 5), 2),
+                int(Span::new(0, 1), 3),
+                int(Span::new(4, 5), 2),
             ),
             1,
         );
 
         // overflow
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 15),
                 BinaryOp::Sub,
-                Expr::int(Span::new(0, 10), 2147483647),
-                Expr::int(Span::new(13, 15), -1),
+                int(Span::new(0, 10), 2147483647),
+                int(Span::new(13, 15), -1),
             ),
             -2147483648,
         );
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 15),
                 BinaryOp::Sub,
-                Expr::int(Span::new(0, 11), -2147483648),
-                Expr::int(Span::new(14, 15), 1),
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 15), 1),
             ),
             2147483647,
         );
@@ -171,22 +218,22 @@ mod tests {
     #[test]
     fn evals_binary_expr_mul() {
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Mul,
-                Expr::int(Span::new(0, 1), 2),
-                Expr::int(Span::new(4, 5), 3),
+                int(Span::new(0, 1), 2),
+                int(Span::new(4, 5), 3),
             ),
             6,
         );
 
         // overflow
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 16),
                 BinaryOp::Mul,
-                Expr::int(Span::new(0, 11), -2147483648),
-                Expr::int(Span::new(14, 16), -1),
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 16), -1),
             ),
             -2147483648,
         );
@@ -195,33 +242,33 @@ mod tests {
     #[test]
     fn evals_binary_expr_div() {
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Div,
-                Expr::int(Span::new(0, 1), 6),
-                Expr::int(Span::new(4, 5), 3),
+                int(Span::new(0, 1), 6),
+                int(Span::new(4, 5), 3),
             ),
             2,
         );
 
         // overflow
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 16),
                 BinaryOp::Div,
-                Expr::int(Span::new(0, 11), -2147483648),
-                Expr::int(Span::new(14, 16), -1),
+                int(Span::new(0, 11), -2147483648),
+                int(Span::new(14, 16), -1),
             ),
             -2147483648,
         );
 
         // division by zero
         assert_does_not_eval!(
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Div,
-                Expr::int(Span::new(0, 1), 1),
-                Expr::int(Span::new(4, 5), 0),
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 0),
             ),
             Error::new(
                 SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(5, 1, 6)),
@@ -233,29 +280,137 @@ mod tests {
     #[test]
     fn evals_complex_expressions() {
         assert_evals!(
-            Expr::binary(
+            binary(
                 Span::new(0, 17),
                 BinaryOp::Mul,
-                Expr::group(
+                group(
                     Span::new(0, 7),
-                    Expr::binary(
+                    binary(
                         Span::new(1, 6),
                         BinaryOp::Add,
-                        Expr::int(Span::new(1, 2), 1),
-                        Expr::int(Span::new(5, 6), 2),
+                        int(Span::new(1, 2), 1),
+                        int(Span::new(5, 6), 2),
                     ),
                 ),
-                Expr::group(
+                group(
                     Span::new(10, 17),
-                    Expr::binary(
+                    binary(
                         Span::new(11, 16),
                         BinaryOp::Add,
-                        Expr::int(Span::new(11, 12), 3),
-                        Expr::int(Span::new(15, 16), 4),
+                        int(Span::new(11, 12), 3),
+                        int(Span::new(15, 16), 4),
                     ),
                 ),
             ),
             21,
         );
     }
+
+    #[test]
+    fn records_reductions() {
+        let source_map = SourceMap::new();
+        // (1 + 2) * 3
+        let ast = ast(binary(
+            Span::new(0, 11),
+            BinaryOp::Mul,
+            group(
+                Span::new(0, 7),
+                binary(
+                    Span::new(1, 6),
+                    BinaryOp::Add,
+                    int(Span::new(1, 2), 1),
+                    int(Span::new(5, 6), 2),
+                ),
+            ),
+            int(Span::new(10, 11), 3),
+        ));
+        let evaluator = Evaluator::new(&ast, &source_map, Limits::default());
+
+        let (result, reductions) = evaluator.eval_traced();
+        assert_eq!(result, Ok(9));
+        assert_eq!(
+            reductions
+                .iter()
+                .map(|r| (r.left(), r.op(), r.right(), r.value(), r.span()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    1,
+                    BinaryOp::Add,
+                    2,
+                    3,
+                    SourceSpan::new(SourcePos::new(1, 1, 2), SourcePos::new(6, 1, 7)),
+                ),
+                (
+                    3,
+                    BinaryOp::Mul,
+                    3,
+                    9,
+                    SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(11, 1, 12)),
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn records_reductions_before_errors() {
+        let source_map = SourceMap::new();
+        // 1 / (2 - 2)
+        let ast = ast(binary(
+            Span::new(0, 11),
+            BinaryOp::Div,
+            int(Span::new(0, 1), 1),
+            group(
+                Span::new(4, 11),
+                binary(
+                    Span::new(5, 10),
+                    BinaryOp::Sub,
+                    int(Span::new(5, 6), 2),
+                    int(Span::new(9, 10), 2),
+                ),
+            ),
+        ));
+        let evaluator = Evaluator::new(&ast, &source_map, Limits::default());
+
+        let (result, reductions) = evaluator.eval_traced();
+        assert_eq!(result.unwrap_err().message(), "division by zero");
+        assert_eq!(reductions.len(), 1);
+        assert_eq!(reductions[0].value(), 0);
+    }
+
+    #[test]
+    fn enforces_steps_limit() {
+        let limits = Limits {
+            max_steps: 3,
+            ..Limits::default()
+        };
+
+        let source_map = SourceMap::new();
+        let ast_1 = ast(binary(
+            Span::new(0, 5),
+            BinaryOp::Add,
+            int(Span::new(0, 1), 1),
+            int(Span::new(4, 5), 2),
+        ));
+        let evaluator = Evaluator::new(&ast_1, &source_map, limits);
+        assert_eq!(evaluator.eval(), Ok(3));
+
+        let ast_2 = ast(group(
+            Span::new(0, 7),
+            binary(
+                Span::new(1, 6),
+                BinaryOp::Add,
+                int(Span::new(1, 2), 1),
+                int(Span::new(5, 6), 2),
+            ),
+        ));
+        let evaluator = Evaluator::new(&ast_2, &source_map, limits);
+        assert_eq!(
+            evaluator.eval(),
+            Err(Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(5, 1, 6), SourcePos::new(6, 1, 7)),
+                Limit::Steps,
+            )),
+        );
+    }
 }
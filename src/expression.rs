@@ -0,0 +1,191 @@
+use alloc::sync::Arc;
+
+use crate::ast::Ast;
+use crate::bytecode::Bytecode;
+use crate::closure_compiler::ClosureCompiler;
+use crate::compiled_expression::CompiledExpression;
+use crate::compiler::Compiler;
+use crate::error::Error;
+use crate::evaluator::Evaluator;
+use crate::limits::Limits;
+use crate::optimizer::Optimizer;
+use crate::source::{SourceMap, SourceSpan, Span};
+use crate::trace::Trace;
+
+/// Parsed expression.
+///
+/// An expression is created by [`sari::parse`](crate::parse). It can be
+/// evaluated repeatedly without parsing the input again.
+///
+/// # Examples
+///
+/// ```
+/// let expr = sari::parse("(1 + 2) * 3")?;
+///
+/// assert_eq!(expr.eval(), Ok(9));
+/// # Ok::<(), sari::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Expression {
+    ast: Ast,
+    source_map: Arc<SourceMap>,
+    limits: Limits,
+}
+
+impl Expression {
+    pub(crate) fn new(ast: Ast, source_map: Arc<SourceMap>, limits: Limits) -> Expression {
+        Expression {
+            ast,
+            source_map,
+            limits,
+        }
+    }
+
+    /// Returns the abstract syntax tree of the expression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::ast::Expr;
+    ///
+    /// let expr = sari::parse("(1 + 2) * 3")?;
+    /// let ast = expr.ast();
+    ///
+    /// assert!(matches!(ast[ast.root()], Expr::Binary(_)));
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn ast(&self) -> &Ast {
+        &self.ast
+    }
+
+    /// Converts a span from the abstract syntax tree of the expression to a
+    /// [`SourceSpan`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::{SourcePos, SourceSpan};
+    ///
+    /// let expr = sari::parse("1 +\n2")?;
+    /// let ast = expr.ast();
+    ///
+    /// assert_eq!(
+    ///     expr.source_span(ast[ast.root()].span()),
+    ///     SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(5, 2, 2)),
+    /// );
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn source_span(&self, span: Span) -> SourceSpan {
+        self.source_map.map_span(span)
+    }
+
+    /// Evaluates the expression and returns the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the evaluation fails, including when it exceeds
+    /// [`Limits::max_steps`] of the limits the expression was parsed with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let expr = sari::parse("(1 + 2) * 3")?;
+    /// assert_eq!(expr.eval(), Ok(9));
+    ///
+    /// let expr = sari::parse("1 / 0")?;
+    /// assert_eq!(expr.eval().unwrap_err().message(), "division by zero");
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn eval(&self) -> Result<i32, Error> {
+        Evaluator::new(&self.ast, &self.source_map, self.limits).eval()
+    }
+
+    /// Evaluates the expression step by step and returns a trace of the
+    /// evaluation.
+    ///
+    /// The evaluation is the same as in [`eval`](Expression::eval), but each
+    /// reduction of a binary expression is recorded. See [`Trace`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let trace = sari::parse("(1 + 2) * 3")?.eval_traced();
+    /// assert_eq!(trace.result(), Ok(9));
+    /// assert_eq!(trace.to_string(), "(1 + 2) * 3 → 3 * 3 → 9");
+    ///
+    /// let trace = sari::parse("1 + 1 / (2 - 2)")?.eval_traced();
+    /// assert_eq!(trace.result().unwrap_err().message(), "division by zero");
+    /// assert_eq!(trace.to_string(), "1 + 1 / (2 - 2) → 1 + 1 / 0");
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn eval_traced(&self) -> Trace {
+        let evaluator = Evaluator::new(&self.ast, &self.source_map, self.limits);
+        let (result, reductions) = evaluator.eval_traced();
+
+        Trace::new(self.ast.clone(), reductions, result)
+    }
+
+    /// Returns an optimized version of the expression.
+    ///
+    /// The optimization folds constant subexpressions, removes parentheses, and
+    /// applies simple algebraic identities (`x + 0`, `x * 1`, etc.). It never
+    /// changes the result of the evaluation, including any errors and their
+    /// spans, with one exception: the optimized expression has fewer nodes, so
+    /// its evaluation takes fewer steps and may stay within
+    /// [`Limits::max_steps`] where the original one exceeds it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let expr = sari::parse("(1 + 2) * 3")?.optimize();
+    /// assert_eq!(expr.eval(), Ok(9));
+    ///
+    /// let expr = sari::parse("(1 / 0) * 0")?.optimize();
+    /// assert_eq!(expr.eval().unwrap_err().message(), "division by zero");
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn optimize(&self) -> Expression {
+        let ast = Optimizer::new(&self.ast).optimize();
+
+        Expression::new(ast, Arc::clone(&self.source_map), self.limits)
+    }
+
+    /// Compiles the expression to bytecode.
+    ///
+    /// See [`Bytecode`] for details. The bytecode doesn't count evaluation
+    /// steps, so [`Limits::max_steps`] isn't enforced when it is evaluated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let bytecode = sari::parse("(1 + 2) * 3")?.to_bytecode();
+    ///
+    /// assert_eq!(bytecode.eval(), Ok(9));
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn to_bytecode(&self) -> Bytecode {
+        let program = Compiler::new(&self.ast).compile();
+
+        Bytecode::new(program, Arc::clone(&self.source_map))
+    }
+
+    /// Compiles the expression to closures.
+    ///
+    /// See [`CompiledExpression`] for details. The closures don't count
+    /// evaluation steps, so [`Limits::max_steps`] isn't enforced when they
+    /// are evaluated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let compiled = sari::parse("(1 + 2) * 3")?.compile();
+    ///
+    /// assert_eq!(compiled.eval(), Ok(9));
+    /// # Ok::<(), sari::Error>(())
+    /// ```
+    pub fn compile(&self) -> CompiledExpression {
+        let closure = ClosureCompiler::new(&self.ast, &self.source_map).compile();
+
+        CompiledExpression::new(closure)
+    }
+}
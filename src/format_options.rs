@@ -0,0 +1,26 @@
+/// Options for formatting expressions.
+///
+/// # Examples
+///
+/// ```
+/// use sari::FormatOptions;
+///
+/// let options = FormatOptions {
+///     max_width: Some(12),
+///     ..FormatOptions::default()
+/// };
+///
+/// let formatted = sari::format_with_options("1+2*3+4*5", options)?;
+/// assert_eq!(formatted, "1\n    + 2 * 3\n    + 4 * 5");
+/// # Ok::<(), sari::Error>(())
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct FormatOptions {
+    /// Maximum line width.
+    ///
+    /// Expressions that don't fit on one line are wrapped before binary
+    /// operators and inside parentheses. The width is not guaranteed, e.g. a
+    /// long integer literal can exceed it. If `None`, expressions are never
+    /// wrapped (except after line comments). The default is `None`.
+    pub max_width: Option<usize>,
+}
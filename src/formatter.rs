@@ -0,0 +1,437 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ast::{BinaryOp, Expr, ExprId};
+use crate::cst::{Cst, CstNode, SyntaxToken, Trivia};
+use crate::doc::{Doc, Printer};
+use crate::format_options::FormatOptions;
+use crate::token::TokenKind;
+
+pub struct Formatter<'a> {
+    cst: &'a Cst,
+    options: FormatOptions,
+}
+
+// Position of an operand relative to its parent binary operator.
+#[derive(Copy, Clone)]
+enum Operand {
+    Root,
+    Left(BinaryOp),
+    Right(BinaryOp),
+}
+
+impl Formatter<'_> {
+    pub fn new(cst: &Cst, options: FormatOptions) -> Formatter<'_> {
+        Formatter { cst, options }
+    }
+
+    pub fn format(&self) -> String {
+        let mut docs = vec![self.format_operand(self.cst.root(), Operand::Root)];
+
+        // Comments at the end of input always start on a new line. Comments on
+        // the same line stay together, the same way the printer lays out
+        // comments of removed parentheses at the end of input.
+        let mut lines: Vec<String> = vec![];
+        let mut new_line = true;
+        for trivia in self.cst.eof().leading_trivia() {
+            match trivia.kind() {
+                TokenKind::Newline => new_line = true,
+                TokenKind::LineComment | TokenKind::BlockComment => {
+                    match lines.last_mut() {
+                        Some(line) if !new_line => {
+                            line.push(' ');
+                            line.push_str(trivia.text());
+                        }
+                        _ => lines.push(trivia.text().into()),
+                    }
+                    new_line = false;
+                }
+                _ => {}
+            }
+        }
+        docs.extend(lines.into_iter().map(Doc::LinePrefix));
+
+        Printer::new(self.options.max_width).print(&Doc::Concat(docs))
+    }
+
+    // Formats an operand, removing all parentheses around it except the ones
+    // necessary to keep the structure of the expression. Comments attached to
+    // the removed parentheses are kept.
+    fn format_operand(&self, id: ExprId, operand: Operand) -> Doc {
+        let mut groups = vec![];
+        let mut id = id;
+        while let CstNode::Group {
+            l_paren,
+            expr,
+            r_paren,
+        } = &self.cst[id]
+        {
+            groups.push((l_paren, r_paren));
+            id = *expr;
+        }
+
+        let keep_parens = self.needs_parens(id, operand);
+        let mut doc = self.format_node(id);
+
+        for (i, (l_paren, r_paren)) in groups.into_iter().enumerate().rev() {
+            doc = if i == 0 && keep_parens {
+                Doc::Group(vec![
+                    self.format_token(l_paren),
+                    Doc::Indent(vec![Doc::SoftLine, doc]),
+                    Doc::SoftLine,
+                    self.format_token(r_paren),
+                ])
+            } else {
+                Doc::Concat(vec![
+                    leading_comments(l_paren, true),
+                    doc,
+                    removed_trailing_comments(r_paren),
+                ])
+            };
+        }
+
+        doc
+    }
+
+    fn format_node(&self, id: ExprId) -> Doc {
+        match &self.cst[id] {
+            CstNode::Int { int } => self.format_token(int),
+            CstNode::Group { .. } => unreachable!("groups are handled by format_operand"),
+            CstNode::Binary { .. } => self.format_binary_chain(id),
+        }
+    }
+
+    // Formats a chain of left-associated operators with the same precedence
+    // (e.g. `1 + 2 - 3`) as one group, so that it is either printed on one
+    // line or wrapped before each operator.
+    fn format_binary_chain(&self, id: ExprId) -> Doc {
+        let precedence = self.binary_op(id).map(|op| op.precedence());
+
+        let mut rest = vec![];
+        let mut id = id;
+        while let CstNode::Binary { left, op, right } = &self.cst[id] {
+            let binary_op = self.binary_op(id).expect("binary expression");
+            rest.push((op, binary_op, *right));
+
+            id = *left;
+            if self.binary_op(id).map(|op| op.precedence()) != precedence {
+                break;
+            }
+        }
+
+        let (_, first_op, _) = rest.last().expect("binary expression");
+        let first = self.format_operand(id, Operand::Left(*first_op));
+
+        let mut docs = vec![];
+        for (op, binary_op, right) in rest.into_iter().rev() {
+            docs.push(Doc::Line);
+            docs.push(self.format_token(op));
+            docs.push(Doc::text(" "));
+            docs.push(self.format_operand(right, Operand::Right(binary_op)));
+        }
+
+        Doc::Group(vec![first, Doc::Indent(docs)])
+    }
+
+    fn format_token(&self, token: &SyntaxToken) -> Doc {
+        Doc::Concat(vec![
+            leading_comments(token, false),
+            Doc::text(token.text()),
+            trailing_comments(token),
+        ])
+    }
+
+    fn needs_parens(&self, id: ExprId, operand: Operand) -> bool {
+        let Some(op) = self.binary_op(id) else {
+            return false;
+        };
+
+        match operand {
+            Operand::Root => false,
+            Operand::Left(parent_op) => op.precedence() < parent_op.precedence(),
+            Operand::Right(parent_op) => op.precedence() <= parent_op.precedence(),
+        }
+    }
+
+    fn binary_op(&self, id: ExprId) -> Option<BinaryOp> {
+        match &self.cst.ast()[id] {
+            Expr::Binary(expr) => Some(expr.op),
+            _ => None,
+        }
+    }
+}
+
+// Comments keep their position relative to the tokens, so that formatting the
+// output again gives the same result. Line comments always end the line, block
+// comments are surrounded by spaces.
+
+// Formats comments before a token. If `all` is true, comments after the token
+// are included too (used when the token itself is removed).
+fn leading_comments(token: &SyntaxToken, all: bool) -> Doc {
+    let mut trivia = token.leading_trivia().to_vec();
+    if all {
+        trivia.extend_from_slice(token.trailing_trivia());
+    }
+
+    let mut docs = vec![];
+    for comment in comments(&trivia) {
+        docs.push(match comment.kind() {
+            TokenKind::LineComment => Doc::LinePrefix(comment.text().into()),
+            _ => block_comment(comment),
+        });
+    }
+
+    Doc::Concat(docs)
+}
+
+// Formats comments after a token.
+fn trailing_comments(token: &SyntaxToken) -> Doc {
+    let mut docs = vec![];
+    for comment in comments(token.trailing_trivia()) {
+        docs.push(match comment.kind() {
+            TokenKind::LineComment => Doc::LineSuffix(comment.text().into()),
+            _ => block_comment(comment),
+        });
+    }
+
+    Doc::Concat(docs)
+}
+
+// Formats comments of a removed closing parenthesis. Block comments are
+// printed before the following token, so that they are laid out the same as
+// when the output is parsed again and they are attached to that token.
+fn removed_trailing_comments(token: &SyntaxToken) -> Doc {
+    let mut trivia = token.leading_trivia().to_vec();
+    trivia.extend_from_slice(token.trailing_trivia());
+
+    let mut docs = vec![];
+    for comment in comments(&trivia) {
+        docs.push(match comment.kind() {
+            TokenKind::LineComment => Doc::LineSuffix(comment.text().into()),
+            _ => Doc::Prefix(comment.text().into()),
+        });
+    }
+
+    Doc::Concat(docs)
+}
+
+fn block_comment(comment: &Trivia) -> Doc {
+    Doc::Concat(vec![
+        Doc::text(" "),
+        Doc::text(comment.text()),
+        Doc::text(" "),
+    ])
+}
+
+fn comments(trivia: &[Trivia]) -> impl Iterator<Item = &Trivia> {
+    trivia.iter().filter(|trivia| {
+        matches!(
+            trivia.kind(),
+            TokenKind::LineComment | TokenKind::BlockComment
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::cst_builder::CstBuilder;
+    use crate::limits::Limits;
+    use crate::parser::Parser;
+    use crate::source::SourceMap;
+
+    fn format(input: &str, options: FormatOptions) -> String {
+        let ast = Parser::new(input, SourceMap::new(), Limits::default())
+            .parse()
+            .unwrap();
+        let cst = CstBuilder::new(input, ast).build();
+
+        Formatter::new(&cst, options).format()
+    }
+
+    // Also checks that formatting the output again doesn't change it.
+    macro_rules! assert_formats {
+        ($input:expr, $output:expr $(,)?) => {
+            assert_formats!($input, $output, FormatOptions::default());
+        };
+        ($input:expr, $output:expr, $options:expr $(,)?) => {
+            let output = format($input, $options);
+
+            assert_eq!(output, $output);
+            assert_eq!(format(&output, $options), output);
+        };
+    }
+
+    fn width(max_width: usize) -> FormatOptions {
+        FormatOptions {
+            max_width: Some(max_width),
+        }
+    }
+
+    #[test]
+    fn normalizes_whitespace() {
+        assert_formats!("1", "1");
+        assert_formats!("  1  ", "1");
+        assert_formats!("1+2*3", "1 + 2 * 3");
+        assert_formats!("1\n+\t2\r\n*  3\n", "1 + 2 * 3");
+    }
+
+    #[test]
+    fn removes_unnecessary_parens() {
+        assert_formats!("(1)", "1");
+        assert_formats!("((1))", "1");
+        assert_formats!("(1 + 2)", "1 + 2");
+        assert_formats!("(1 + 2) + 3", "1 + 2 + 3");
+        assert_formats!("(1 * 2) + 3", "1 * 2 + 3");
+        assert_formats!("1 + (2 * 3)", "1 + 2 * 3");
+        assert_formats!("(1 * 2) * 3", "1 * 2 * 3");
+        assert_formats!("1 * (2)", "1 * 2");
+    }
+
+    #[test]
+    fn keeps_necessary_parens() {
+        assert_formats!("(1 + 2) * 3", "(1 + 2) * 3");
+        assert_formats!("1 * (2 + 3)", "1 * (2 + 3)");
+        assert_formats!("1 - (2 - 3)", "1 - (2 - 3)");
+        assert_formats!("1 + (2 + 3)", "1 + (2 + 3)");
+        assert_formats!("1 / (2 * 3)", "1 / (2 * 3)");
+        assert_formats!("((1 + 2)) * 3", "(1 + 2) * 3");
+        assert_formats!("( 1+2 )*( 3-4 )", "(1 + 2) * (3 - 4)");
+    }
+
+    #[test]
+    fn wraps_long_expressions() {
+        assert_formats!("1 + 2 + 3", "1 + 2 + 3", width(9));
+        assert_formats!("1 + 2 + 3", "1\n    + 2\n    + 3", width(8));
+        assert_formats!(
+            "111 * 222 + 333 * 444",
+            "111 * 222\n    + 333 * 444",
+            width(15),
+        );
+        assert_formats!("(111 + 222) * 333", "(111 + 222)\n    * 333", width(12));
+        assert_formats!(
+            "(111 + 222 + 333) * 4",
+            "(\n    111\n        + 222\n        + 333\n)\n    * 4",
+            width(10),
+        );
+        assert_formats!(
+            "1000 * (111 + 222 + 333)",
+            "1000\n    * (111 + 222 + 333)",
+            width(23),
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        assert_formats!("1 /* a */ + 2", "1 /* a */ + 2");
+        assert_formats!("1 + /* a */ 2", "1 + /* a */ 2");
+        assert_formats!("/* a */ 1", "/* a */ 1");
+        assert_formats!("1 + 2 // a", "1 + 2 // a");
+        assert_formats!("// a\n1 + 2", "// a\n1 + 2");
+        assert_formats!("1 + 2\n// a", "1 + 2\n// a");
+        assert_formats!("1\n/* a */ /* b */\n// c", "1\n/* a */ /* b */\n// c");
+        assert_formats!("1 // a\n+ 2", "1 // a\n    + 2");
+        assert_formats!("1 + // a\n2", "1 + // a\n    2");
+        assert_formats!("1 +\n// a\n2", "1 +\n    // a\n    2");
+        assert_formats!("1 + // a\n2 * 3", "1 + // a\n    2 * 3");
+        assert_formats!("1 - // a\n2 / 3 - 4", "1 - // a\n    2 / 3 - 4");
+        assert_formats!("1 + 2 * // a\n3", "1 + 2 * // a\n        3");
+        assert_formats!("1 // a\n// b\n+ 2", "1 // a\n    // b\n    + 2");
+        assert_formats!("1 +\n// a\n// b\n2", "1 +\n    // a\n    // b\n    2");
+        assert_formats!(
+            "1 + // a\n2 * 3 // b\n+ 4 // c",
+            "1 + // a\n    2 * 3 // b\n    + 4 // c",
+        );
+    }
+
+    #[test]
+    fn keeps_comments_of_removed_parens() {
+        assert_formats!("(/* a */ 1)", "/* a */ 1");
+        assert_formats!("(1) /* a */", "1 /* a */");
+        assert_formats!("( // a\n1)", "// a\n1");
+        assert_formats!("(1 // a\n)", "1 // a");
+        assert_formats!("(1 // a\n) // b", "1 // a\n// b");
+        assert_formats!("(1\n// a\n) * 2", "1 // a\n    * 2");
+        assert_formats!("(1 + 2 // a\n) * 3", "(1 + 2 // a\n) * 3");
+        assert_formats!("(1 // a\n) /* b */ /* c */", "1 // a\n/* b */ /* c */");
+        assert_formats!(
+            "/* a */(// b\n 1 // c\n)/* d */ /* e */",
+            "/* a */\n// b\n1 // c\n/* d */ /* e */",
+        );
+    }
+
+    #[test]
+    fn formats_long_lines() {
+        // Formatting used to rescan the current line for each token, so one
+        // long line took quadratic time.
+        let input = vec!["1"; 100000].join(" + ");
+
+        assert_eq!(format(&input, FormatOptions::default()), input);
+        assert!(
+            format(&input, width(80))
+                .lines()
+                .all(|line| line.len() <= 80)
+        );
+    }
+
+    #[test]
+    fn formats_comments_idempotently() {
+        let inputs = [
+            "1 + 2 * 3",
+            "( 1 + 2 ) * 3",
+            "1 - ( 2 - 3 ) / 4",
+            "( ( 1 ) ) + ( 2 * ( 3 ) )",
+            "111 * 222 + 333 * 444 - 555",
+        ];
+        let kinds = [" // {}\n", "\n// {}\n", " /* {} */ ", "\n/* {} */\n"];
+
+        for input in inputs {
+            let tokens: Vec<_> = input.split(' ').collect();
+            let gaps = tokens.len() + 1;
+
+            // Gaps between the tokens to insert comments into: two comments
+            // anywhere, or three comments close to each other (e.g. a line
+            // comment followed by two block comments after `)`).
+            let mut placements = vec![];
+            for i in 0..gaps {
+                for j in i..gaps {
+                    placements.push(vec![i, j]);
+                }
+                for (j, k) in [(i, i), (i, i + 1), (i + 1, i + 1)] {
+                    if k < gaps {
+                        placements.push(vec![i, j, k]);
+                    }
+                }
+            }
+
+            for placement in placements {
+                for kind in 0..kinds.len().pow(placement.len() as u32) {
+                    let mut comments = vec![String::new(); gaps];
+                    for (n, gap) in placement.iter().enumerate() {
+                        let kind = kinds[kind / kinds.len().pow(n as u32) % kinds.len()];
+                        let name = char::from(b'a' + n as u8).to_string();
+                        comments[*gap].push_str(&kind.replace("{}", &name));
+                    }
+
+                    let mut input = String::new();
+                    for (gap, comments) in comments.iter().enumerate() {
+                        input.push_str(comments);
+                        if let Some(token) = tokens.get(gap) {
+                            input.push_str(token);
+                            input.push(' ');
+                        }
+                    }
+
+                    for options in [FormatOptions::default(), width(1), width(12)] {
+                        let output = format(&input, options);
+
+                        assert_eq!(format(&output, options), output, "input: {input:?}");
+                    }
+                }
+            }
+        }
+    }
+}
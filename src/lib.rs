@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 
 //! Simple arithmetic expression evaluator.
@@ -17,33 +18,175 @@
 //! assert_eq!(result.unwrap_err().message(), "division by zero");
 //! ```
 //!
+//! To evaluate an expression repeatedly, parse it once using the `sari::parse`
+//! function and then evaluate the resulting [`Expression`]:
+//!
+//! ```
+//! let expr = sari::parse("(1 + 2) * 3")?;
+//!
+//! assert_eq!(expr.eval(), Ok(9));
+//! assert_eq!(expr.optimize().eval(), Ok(9));
+//! # Ok::<(), sari::Error>(())
+//! ```
+//!
+//! To evaluate many expressions with the same configuration (e.g. custom
+//! limits), create an [`Engine`]. It can be shared between threads, and so can
+//! the expressions it parses:
+//!
+//! ```
+//! use sari::{Engine, Limits};
+//!
+//! let engine = Engine::new().with_limits(Limits {
+//!     max_input_len: 1000,
+//!     ..Limits::default()
+//! });
+//!
+//! assert_eq!(engine.eval("(1 + 2) * 3"), Ok(9));
+//! ```
+//!
+//! Each thread can also create a [`Session`] from the engine, which reuses
+//! memory between evaluations.
+//!
 //! # Expressions
 //!
 //! The expressions consist of integers combined using `+`, `-`, `*`, and `/`
 //! binary operators (with the usual precedence and associativity) and grouped
-//! using parentheses. These elements can be separated by whitespace.
+//! using parentheses. These elements can be separated by whitespace and
+//! comments, which are either line comments (`// ...`) or block comments
+//! (`/* ... */`).
 //!
 //! The expressions use wrapping 32-bit signed arithmetic. Division by zero is
 //! an error.
+//!
+//! Parentheses can be nested at most 256 levels deep. Deeper nesting is an
+//! error. Chains of binary operators, such as `1 + 2 + 3`, don't count towards
+//! this limit. This and other limits on untrusted input can be adjusted using
+//! [`parse_with_limits`].
+//!
+//! # Features
+//!
+//! The `std` feature (enabled by default) makes the crate depend on the
+//! standard library. Without it, the crate is `no_std` and needs only `alloc`,
+//! so it can be used on targets without an operating system. The `sari` binary
+//! requires `std`.
+//!
+//! The `serde` feature (disabled by default) implements `Serialize` and
+//! `Deserialize` for the [AST](ast), [`Span`], [`SourcePos`], [`SourceSpan`],
+//! and [`Error`]. The JSON shape may change between versions. For example,
+//! the AST of `1 + 2` is:
+//!
+//! ```json
+//! {
+//!   "exprs": [
+//!     { "kind": "int", "span": { "start": 0, "end": 1 }, "value": 1 },
+//!     { "kind": "int", "span": { "start": 4, "end": 5 }, "value": 2 },
+//!     {
+//!       "kind": "binary",
+//!       "span": { "start": 0, "end": 5 },
+//!       "op": "add",
+//!       "left": 0,
+//!       "right": 1
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Expressions are stored in an arena and refer to their children by index.
+//! The last expression is the root. Expression kinds are `int`, `group` (with
+//! an `expr` field), and `binary`, and operators are `add`, `sub`, `mul`, and
+//! `div`. Deserialization fails if an expression refers to an expression that
+//! doesn't precede it.
+//!
+//! An error of `1 / 0` is:
+//!
+//! ```json
+//! {
+//!   "span": {
+//!     "start": {
+//!       "offset": 0,
+//!       "line": 1,
+//!       "column": 1,
+//!       "byte_offset": 0,
+//!       "utf16_column": 1
+//!     },
+//!     "end": {
+//!       "offset": 5,
+//!       "line": 1,
+//!       "column": 6,
+//!       "byte_offset": 5,
+//!       "utf16_column": 6
+//!     }
+//!   },
+//!   "message": "division by zero",
+//!   "limit": null
+//! }
+//! ```
+//!
+//! The `limit` field is one of `input_len`, `tokens`, `nodes`, `depth`, and
+//! `steps` for errors reporting an exceeded limit (see [`Error::limit`]).
+//! Errors of expressions from a [`SourceDb`] also have a `source` field, e.g.
+//! `{ "id": 0, "name": "pricing.sari" }`.
+
+extern crate alloc;
 
-mod ast;
+pub mod ast;
+mod bytecode;
+mod closure_compiler;
+mod compiled_expression;
+mod compiler;
+pub mod cst;
+mod cst_builder;
+mod doc;
+mod engine;
 mod error;
 mod evaluator;
+mod expression;
+mod format_options;
+mod formatter;
+mod limits;
+mod one_pass_evaluator;
+mod optimizer;
 mod parser;
+mod program;
 mod scanner;
+mod session;
 mod source;
+mod source_db;
 mod token;
+mod tokens;
+mod trace;
+pub mod visit;
+mod vm;
 
-use std::cell::RefCell;
-use std::rc::Rc;
+use alloc::string::String;
 
+#[doc(inline)]
+pub use bytecode::Bytecode;
+#[doc(inline)]
+pub use compiled_expression::CompiledExpression;
+use cst::Cst;
+#[doc(inline)]
+pub use engine::Engine;
 #[doc(inline)]
 pub use error::Error;
-use evaluator::Evaluator;
-use parser::Parser;
-use source::SourceMap;
 #[doc(inline)]
-pub use source::{SourcePos, SourceSpan};
+pub use expression::Expression;
+#[doc(inline)]
+pub use format_options::FormatOptions;
+#[doc(inline)]
+pub use limits::{Limit, Limits};
+#[doc(inline)]
+pub use session::Session;
+#[doc(inline)]
+pub use source::{SourcePos, SourceSpan, Span};
+#[doc(inline)]
+pub use source_db::{SourceDb, SourceId};
+#[doc(inline)]
+pub use token::TokenKind;
+#[doc(inline)]
+pub use tokens::{SourceToken, Tokens};
+#[doc(inline)]
+pub use trace::{Reduction, Trace};
 
 /// Evaluates an expression and returns the result.
 ///
@@ -64,10 +207,212 @@ pub use source::{SourcePos, SourceSpan};
 /// assert_eq!(result.unwrap_err().message(), "division by zero");
 /// ```
 pub fn eval(expr: &str) -> Result<i32, Error> {
-    let source_map = Rc::new(RefCell::new(SourceMap::new()));
+    parse(expr)?.eval()
+}
+
+/// Evaluates an expression in one pass and returns the result.
+///
+/// Unlike [`eval`], this function doesn't build the AST. It scans, parses, and
+/// evaluates the expression at once, using a fixed amount of stack memory, and
+/// it doesn't allocate unless the evaluation fails. This makes it suitable for
+/// hot paths where heap allocation is undesirable.
+///
+/// The stack memory holds operands and operators for the deepest nesting of
+/// parentheses allowed by the default [`Limits`], which takes about 25 KB on
+/// 64-bit targets. Make sure the calling thread has enough stack to spare.
+///
+/// The result, including the error and its span, is always the same as the
+/// result of [`eval`].
+///
+/// # Errors
+///
+/// Returns [`Error`] if the evaluation fails.
+///
+/// # Examples
+///
+/// ```
+/// let result = sari::eval_one_pass("(1 + 2) * 3");
+/// assert_eq!(result, Ok(9));
+///
+/// let result = sari::eval_one_pass("(1 + 2");
+/// assert_eq!(result, sari::eval("(1 + 2"));
+/// ```
+pub fn eval_one_pass(expr: &str) -> Result<i32, Error> {
+    one_pass_evaluator::OnePassEvaluator::new(expr).eval()
+}
 
-    let ast = Parser::new(expr, Rc::clone(&source_map)).parse()?;
-    let value = Evaluator::new(&ast, Rc::clone(&source_map)).eval()?;
+/// Evaluates an expression step by step and returns a trace of the
+/// evaluation.
+///
+/// See [`Trace`] for details.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the parsing fails. Errors of the evaluation itself are
+/// reported by [`Trace::result`].
+///
+/// # Examples
+///
+/// ```
+/// let trace = sari::eval_traced("(1 + 2) * 3")?;
+///
+/// assert_eq!(trace.result(), Ok(9));
+/// assert_eq!(trace.to_string(), "(1 + 2) * 3 → 3 * 3 → 9");
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub fn eval_traced(expr: &str) -> Result<Trace, Error> {
+    Ok(parse(expr)?.eval_traced())
+}
 
-    Ok(value)
+/// Parses an expression and returns it.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the parsing fails.
+///
+/// # Examples
+///
+/// ```
+/// let expr = sari::parse("(1 + 2) * 3")?;
+/// assert_eq!(expr.eval(), Ok(9));
+///
+/// let result = sari::parse("(1 + 2");
+/// assert_eq!(result.unwrap_err().message(), "expected `)`");
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub fn parse(expr: &str) -> Result<Expression, Error> {
+    parse_with_limits(expr, Limits::default())
+}
+
+/// Parses an expression with resource limits and returns it.
+///
+/// The limits also apply to evaluating the returned expression. See
+/// [`Limits`] for details.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the parsing fails or a limit is exceeded.
+///
+/// # Examples
+///
+/// ```
+/// use sari::{Limit, Limits};
+///
+/// let limits = Limits {
+///     max_tokens: 5,
+///     ..Limits::default()
+/// };
+///
+/// let expr = sari::parse_with_limits("1 + 2 * 3", limits)?;
+/// assert_eq!(expr.eval(), Ok(7));
+///
+/// let result = sari::parse_with_limits("1 + 2 * 3 - 4", limits);
+/// assert_eq!(result.unwrap_err().limit(), Some(Limit::Tokens));
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub fn parse_with_limits(expr: &str, limits: Limits) -> Result<Expression, Error> {
+    Engine::new().with_limits(limits).parse(expr)
+}
+
+/// Parses an expression and returns its concrete syntax tree.
+///
+/// The concrete syntax tree keeps all tokens and trivia of the input, so it
+/// can be converted back to the exact input. See [`cst`] for details.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the parsing fails.
+///
+/// # Examples
+///
+/// ```
+/// let cst = sari::parse_cst(" (1 +\n2)*3 ")?;
+///
+/// assert_eq!(cst.to_string(), " (1 +\n2)*3 ");
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub fn parse_cst(expr: &str) -> Result<Cst, Error> {
+    Engine::new().parse_cst(expr)
+}
+
+/// Formats an expression.
+///
+/// The formatting normalizes whitespace, puts spaces around binary operators,
+/// and removes unnecessary parentheses. Comments are kept.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the parsing fails.
+///
+/// # Examples
+///
+/// ```
+/// let formatted = sari::format("((1+2))*3 // nine")?;
+///
+/// assert_eq!(formatted, "(1 + 2) * 3 // nine");
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub fn format(expr: &str) -> Result<String, Error> {
+    format_with_options(expr, FormatOptions::default())
+}
+
+/// Formats an expression with options.
+///
+/// See [`format()`] and [`FormatOptions`] for details.
+///
+/// # Errors
+///
+/// Returns [`Error`] if the parsing fails.
+///
+/// # Examples
+///
+/// ```
+/// use sari::FormatOptions;
+///
+/// let options = FormatOptions {
+///     max_width: Some(12),
+///     ..FormatOptions::default()
+/// };
+///
+/// let formatted = sari::format_with_options("1+2*3+4*5", options)?;
+/// assert_eq!(formatted, "1\n    + 2 * 3\n    + 4 * 5");
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub fn format_with_options(expr: &str, options: FormatOptions) -> Result<String, Error> {
+    Engine::new().with_format_options(options).format(expr)
+}
+
+/// Splits an expression into tokens and returns an iterator over them.
+///
+/// The tokenization follows the same lexical rules as
+/// [`sari::parse`](crate::parse), but doesn't require the expression to be
+/// valid. Whitespace is skipped, comments produce [`TokenKind::LineComment`]
+/// and [`TokenKind::BlockComment`] tokens (even if unterminated), characters
+/// that don't start any valid token produce [`TokenKind::Error`] tokens, and
+/// the iteration ends at the end of input (no [`TokenKind::Eof`] token is
+/// produced).
+///
+/// This is useful for tools like syntax highlighters.
+///
+/// # Examples
+///
+/// ```
+/// use sari::TokenKind;
+///
+/// let kinds = sari::tokenize("(1 + ?")
+///     .map(|token| token.kind())
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         TokenKind::LParen,
+///         TokenKind::Int,
+///         TokenKind::Plus,
+///         TokenKind::Error,
+///     ],
+/// );
+/// ```
+pub fn tokenize(expr: &str) -> Tokens<'_> {
+    Tokens::new(expr)
 }
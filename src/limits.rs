@@ -0,0 +1,103 @@
+/// Limits on resources used when parsing and evaluating expressions.
+///
+/// Limits protect against untrusted input that would take too much time or
+/// memory to process. When a limit is exceeded, an [`Error`](crate::Error) is
+/// returned and [`Error::limit`](crate::Error::limit) tells which limit it was.
+///
+/// The default limits don't restrict the input in any way except for the
+/// nesting of parentheses, which is always limited to prevent stack overflows.
+///
+/// # Examples
+///
+/// ```
+/// use sari::{Limit, Limits};
+///
+/// let limits = Limits {
+///     max_input_len: 1000,
+///     ..Limits::default()
+/// };
+///
+/// let result = sari::parse_with_limits(&"1 + ".repeat(1000), limits);
+/// assert_eq!(result.unwrap_err().limit(), Some(Limit::InputLen));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Limits {
+    /// Maximum length of the input in bytes.
+    ///
+    /// The length is checked before scanning, so even very long input is
+    /// rejected quickly.
+    pub max_input_len: usize,
+
+    /// Maximum number of tokens in the input.
+    pub max_tokens: usize,
+
+    /// Maximum number of nodes in the abstract syntax tree.
+    ///
+    /// Each integer literal, parenthesized expression, and binary expression
+    /// is a node.
+    pub max_nodes: usize,
+
+    /// Maximum nesting depth of parentheses.
+    ///
+    /// Chains of operators, such as `1 + 2 + 3`, don't count towards the
+    /// limit. Parsing, formatting, and compiled closures recurse into
+    /// parentheses, so raising this limit too high can overflow the stack.
+    /// The default is 256.
+    pub max_depth: usize,
+
+    /// Maximum number of evaluation steps.
+    ///
+    /// Evaluating each node of the abstract syntax tree is a step. The limit
+    /// is only enforced by [`Expression::eval`](crate::Expression::eval) and
+    /// [`Expression::eval_traced`](crate::Expression::eval_traced). Bytecode
+    /// and compiled closures don't count steps, and an
+    /// [optimized](crate::Expression::optimize) expression takes fewer steps
+    /// than the original one.
+    pub max_steps: usize,
+}
+
+// The default depth limit is also the capacity of the fixed-size stacks used by
+// `OnePassEvaluator`.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 256;
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_input_len: usize::MAX,
+            max_tokens: usize::MAX,
+            max_nodes: usize::MAX,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_steps: usize::MAX,
+        }
+    }
+}
+
+/// Limit that can be exceeded, corresponding to one of the fields of
+/// [`Limits`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Limit {
+    /// [`Limits::max_input_len`] was exceeded.
+    InputLen,
+    /// [`Limits::max_tokens`] was exceeded.
+    Tokens,
+    /// [`Limits::max_nodes`] was exceeded.
+    Nodes,
+    /// [`Limits::max_depth`] was exceeded.
+    Depth,
+    /// [`Limits::max_steps`] was exceeded.
+    Steps,
+}
+
+impl Limit {
+    pub(crate) fn message(&self) -> &'static str {
+        match self {
+            Limit::InputLen => "input too long",
+            Limit::Tokens => "too many tokens",
+            Limit::Nodes => "expression too large",
+            Limit::Depth => "expression nested too deeply",
+            Limit::Steps => "too many evaluation steps",
+        }
+    }
+}
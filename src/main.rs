@@ -1,22 +1,82 @@
 use std::{env, process};
 
+use sari::FormatOptions;
+
+const USAGE: &str = "\
+Usage: sari [--explain] <expr>...
+       sari fmt [--width <width>] <expr>...";
+
 fn main() {
-    let args = env::args();
+    let args = env::args().skip(1).collect::<Vec<_>>();
 
-    if args.len() == 1 {
-        println!("Usage: sari <expr>...");
-        process::exit(1);
+    match args.first().map(String::as_str) {
+        None => usage(),
+        Some("fmt") => fmt(&args[1..]),
+        Some("--explain") => explain(&args[1..]),
+        Some(_) => eval(&args),
     }
+}
 
-    let exprs = args.skip(1);
-
+fn eval(exprs: &[String]) {
     for expr in exprs {
-        match sari::eval(&expr) {
+        match sari::eval(expr) {
             Ok(value) => println!("{value}"),
-            Err(e) => {
-                eprintln!("{e}");
-                process::exit(1);
-            }
+            Err(e) => fail(e),
+        }
+    }
+}
+
+fn explain(exprs: &[String]) {
+    if exprs.is_empty() {
+        usage();
+    }
+
+    for expr in exprs {
+        let trace = match sari::eval_traced(expr) {
+            Ok(trace) => trace,
+            Err(e) => fail(e),
+        };
+
+        println!("{trace}");
+        if let Err(e) = trace.result() {
+            fail(e);
         }
     }
 }
+
+fn fmt(args: &[String]) {
+    let mut options = FormatOptions::default();
+
+    let exprs = match args {
+        [flag, width, exprs @ ..] if flag == "--width" => {
+            let Ok(width) = width.parse() else {
+                fail(format!("invalid width: {width}"));
+            };
+            options.max_width = Some(width);
+
+            exprs
+        }
+        exprs => exprs,
+    };
+
+    if exprs.is_empty() {
+        usage();
+    }
+
+    for expr in exprs {
+        match sari::format_with_options(expr, options) {
+            Ok(formatted) => println!("{formatted}"),
+            Err(e) => fail(e),
+        }
+    }
+}
+
+fn usage() -> ! {
+    println!("{USAGE}");
+    process::exit(1);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
@@ -0,0 +1,370 @@
+use alloc::format;
+use alloc::string::String;
+use core::mem;
+
+use crate::ast::BinaryOp;
+use crate::error::Error;
+use crate::limits::{DEFAULT_MAX_DEPTH, Limit, Limits};
+use crate::scanner::Scanner;
+use crate::source::{Span, Spanned};
+use crate::token::{Token, TokenKind};
+
+// Opening parentheses can't be nested deeper than the depth limit. Each level of
+// nesting (including the top one) holds at most the opening parenthesis and two
+// operators waiting for their right operand (an additive and a multiplicative
+// one), together with their left operands. The operand being parsed comes on
+// top of that.
+const LEVELS: usize = DEFAULT_MAX_DEPTH + 1;
+const MAX_OPERATORS: usize = 3 * LEVELS;
+const MAX_OPERANDS: usize = 2 * LEVELS + 1;
+
+// Evaluator which scans, parses, and evaluates the input in one pass, using
+// operator precedence parsing with fixed-size stacks. It doesn't build an AST or
+// fill in a source map, so it doesn't allocate unless it fails.
+//
+// The evaluator produces the same results and errors as parsing the input with
+// the default limits and evaluating the AST. This means that operators are
+// reduced at the same points of the input as in `Parser` and that evaluation
+// errors are reported only once the whole input is parsed, because parsing
+// errors take precedence.
+pub struct OnePassEvaluator<'a> {
+    scanner: Scanner<'a>,
+    limits: Limits,
+    current: Token,
+    operands: Stack<Operand, MAX_OPERANDS>,
+    operators: Stack<Operator, MAX_OPERATORS>,
+    nesting: usize,
+    // Span of the first division by zero, in the order the AST is evaluated.
+    division_by_zero: Option<Span>,
+}
+
+#[derive(Copy, Clone)]
+struct Operand {
+    value: i32,
+    span: Span,
+}
+
+#[derive(Copy, Clone)]
+enum Operator {
+    Binary(BinaryOp),
+    // Start offset of the opening parenthesis, which is all that is needed for
+    // the span of the group.
+    LParen(usize),
+}
+
+impl OnePassEvaluator<'_> {
+    pub fn new(input: &str) -> OnePassEvaluator<'_> {
+        let limits = Limits::default();
+
+        OnePassEvaluator {
+            scanner: Scanner::without_source_map(input, limits),
+            limits,
+            current: Token::eof(Span::new(0, 0)),
+            operands: Stack::new(Operand {
+                value: 0,
+                span: Span::new(0, 0),
+            }),
+            operators: Stack::new(Operator::LParen(0)),
+            nesting: 0,
+            division_by_zero: None,
+        }
+    }
+
+    pub fn eval(mut self) -> Result<i32, Error> {
+        self.advance()?;
+
+        self.parse_operand()?;
+        while self.parse_operator()? {
+            self.parse_operand()?;
+        }
+
+        let result = self.operands.pop();
+
+        match self.division_by_zero {
+            Some(span) => Err(self.scanner.error(span, "division by zero")),
+            None => Ok(result.value),
+        }
+    }
+
+    // Parses opening parentheses up to an integer literal.
+    fn parse_operand(&mut self) -> Result<(), Error> {
+        loop {
+            match self.current().kind() {
+                TokenKind::Int => {
+                    let int = self.advance()?;
+
+                    self.operands.push(Operand {
+                        value: int.int_value(),
+                        span: int.span(),
+                    });
+
+                    return Ok(());
+                }
+
+                TokenKind::LParen => {
+                    let l_paren = self.advance()?;
+
+                    self.nesting += 1;
+                    if self.nesting > self.limits.max_depth {
+                        return Err(self.scanner.limit_error(l_paren.span(), Limit::Depth));
+                    }
+
+                    self.operators
+                        .push(Operator::LParen(l_paren.span().start()));
+                }
+
+                _ => {
+                    return Err(self.error(
+                        self.current(),
+                        format!(
+                            "expected {} or {}",
+                            TokenKind::Int.as_str(),
+                            TokenKind::LParen.as_str()
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Parses closing parentheses up to a binary operator. Returns `false` at the
+    // end of input.
+    fn parse_operator(&mut self) -> Result<bool, Error> {
+        loop {
+            if let TokenKind::Plus | TokenKind::Minus | TokenKind::Star | TokenKind::Slash =
+                self.current().kind()
+            {
+                let op = BinaryOp::from_token(*self.current());
+                self.reduce(op.precedence());
+
+                self.advance()?;
+                self.operators.push(Operator::Binary(op));
+
+                return Ok(true);
+            }
+
+            self.reduce(0);
+
+            match (self.operators.last(), self.current().kind()) {
+                (Some(Operator::LParen(start)), TokenKind::RParen) => {
+                    let r_paren = self.advance()?;
+
+                    self.operators.pop();
+                    self.nesting -= 1;
+
+                    self.reduce_group(start, r_paren.span());
+                }
+                (Some(_), _) => {
+                    return Err(self.error(
+                        self.current(),
+                        format!("expected {}", TokenKind::RParen.as_str()),
+                    ));
+                }
+                (None, TokenKind::Eof) => return Ok(false),
+                (None, _) => {
+                    return Err(self.error(
+                        self.current(),
+                        format!("expected {}", TokenKind::Eof.as_str()),
+                    ));
+                }
+            }
+        }
+    }
+
+    // Reduces binary operators with at least specified precedence on top of the
+    // stack, up to the nearest opening parenthesis.
+    fn reduce(&mut self, min_precedence: u8) {
+        while let Some(Operator::Binary(op)) = self.operators.last()
+            && op.precedence() >= min_precedence
+        {
+            self.operators.pop();
+
+            self.reduce_binary(op);
+        }
+    }
+
+    fn reduce_group(&mut self, start: usize, r_paren: Span) {
+        let expr = self.operands.pop();
+
+        self.operands.push(Operand {
+            value: expr.value,
+            span: Span::new(start, r_paren.end()),
+        });
+    }
+
+    fn reduce_binary(&mut self, op: BinaryOp) {
+        let right = self.operands.pop();
+        let left = self.operands.pop();
+
+        let span = Span::cover(left.span, right.span);
+
+        // Operators are reduced in the same order the AST is evaluated, so the
+        // first division by zero is the one the evaluator would report.
+        let value = op.apply(left.value, right.value).unwrap_or_else(|| {
+            self.division_by_zero.get_or_insert(span);
+            0
+        });
+
+        self.operands.push(Operand { value, span });
+    }
+
+    fn advance(&mut self) -> Result<Token, Error> {
+        let next = self.scanner.scan()?;
+
+        Ok(mem::replace(&mut self.current, next))
+    }
+
+    fn current(&self) -> &Token {
+        &self.current
+    }
+
+    fn error(&self, spanned: &impl Spanned, message: impl Into<String>) -> Error {
+        self.scanner.error(spanned.span(), message)
+    }
+}
+
+// Stack with a fixed capacity, which doesn't allocate. Slots above the top of
+// the stack hold stale items (initially copies of `empty`), which are never
+// read.
+struct Stack<T, const N: usize> {
+    items: [T; N],
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> Stack<T, N> {
+    fn new(empty: T) -> Stack<T, N> {
+        Stack {
+            items: [empty; N],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, item: T) {
+        assert!(self.len < N, "stack overflow");
+
+        self.items[self.len] = item;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> T {
+        assert!(self.len > 0, "stack underflow");
+
+        self.len -= 1;
+        self.items[self.len]
+    }
+
+    fn last(&self) -> Option<T> {
+        self.len.checked_sub(1).map(|index| self.items[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::{SourcePos, SourceSpan};
+
+    macro_rules! assert_evals {
+        ($input:expr, $value:expr $(,)?) => {
+            let evaluator = OnePassEvaluator::new($input);
+
+            assert_eq!(evaluator.eval(), Ok($value));
+        };
+    }
+
+    macro_rules! assert_does_not_eval {
+        ($input:expr, $error:expr $(,)?) => {
+            let evaluator = OnePassEvaluator::new($input);
+
+            assert_eq!(evaluator.eval(), Err($error));
+        };
+    }
+
+    #[test]
+    fn evals_expressions() {
+        assert_evals!("1", 1);
+        assert_evals!("(1)", 1);
+        assert_evals!("1 + 2 * 3", 7);
+        assert_evals!("(1 + 2) * 3", 9);
+        assert_evals!("7 - 2 - 1", 4);
+        assert_evals!("8 / 2 / 2", 2);
+        assert_evals!("1 * 2 + 3 * 4 - 5 / 5", 13);
+
+        // overflow
+        assert_evals!("2147483647 + 1", -2147483648);
+    }
+
+    #[test]
+    fn does_not_eval_invalid_input() {
+        assert_does_not_eval!(
+            "",
+            Error::new(
+                SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(0, 1, 1)),
+                "expected integer literal or `(`",
+            ),
+        );
+        assert_does_not_eval!(
+            "(1 + 2",
+            Error::new(
+                SourceSpan::new(SourcePos::new(6, 1, 7), SourcePos::new(6, 1, 7)),
+                "expected `)`",
+            ),
+        );
+        assert_does_not_eval!(
+            "1 2",
+            Error::new(
+                SourceSpan::new(SourcePos::new(2, 1, 3), SourcePos::new(3, 1, 4)),
+                "expected end of input",
+            ),
+        );
+    }
+
+    #[test]
+    fn reports_division_by_zero_after_parsing() {
+        assert_does_not_eval!(
+            "1 +\n(2 / 0) / 0",
+            Error::new(
+                SourceSpan::new(SourcePos::new(5, 2, 2), SourcePos::new(10, 2, 7)),
+                "division by zero",
+            ),
+        );
+
+        // parsing errors take precedence
+        assert_does_not_eval!(
+            "1 / 0 +",
+            Error::new(
+                SourceSpan::new(SourcePos::new(7, 1, 8), SourcePos::new(7, 1, 8)),
+                "expected integer literal or `(`",
+            ),
+        );
+    }
+
+    #[test]
+    fn enforces_depth_limit() {
+        let input = format!("{}1{}", "(".repeat(256), ")".repeat(256));
+        assert_evals!(&input, 1);
+
+        let input = format!("{}1", "(".repeat(257));
+        assert_does_not_eval!(
+            &input,
+            Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(256, 1, 257), SourcePos::new(257, 1, 258)),
+                Limit::Depth,
+            ),
+        );
+    }
+
+    #[test]
+    fn fits_in_documented_stack_size() {
+        // See `sari::eval_one_pass`.
+        assert!(mem::size_of::<OnePassEvaluator>() <= 25 * 1024);
+    }
+
+    #[test]
+    fn evals_long_chains_of_operators() {
+        let input = format!("1{}", " + 1".repeat(10000));
+        assert_evals!(&input, 10001);
+
+        let input = format!("1{}", " + 2 * 3".repeat(10000));
+        assert_evals!(&input, 60001);
+    }
+}
@@ -0,0 +1,419 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ast::{Ast, BinaryExpr, BinaryOp, Expr, ExprId, GroupExpr, IntExpr, Step};
+use crate::source::Span;
+
+pub struct Optimizer<'a> {
+    ast: &'a Ast,
+    optimized_ast: Ast,
+}
+
+// Integer literals are added to the optimized AST only once they are used by a
+// parent expression. This way, operands dropped by identities don't leave
+// unreachable expressions behind. Other expressions carry whether evaluating
+// them can fail, which is computed once, when they are added.
+#[derive(Copy, Clone)]
+enum Optimized {
+    Int(Span, i32),
+    Expr(ExprId, bool),
+}
+
+impl Optimized {
+    fn int_value(&self) -> Option<i32> {
+        match self {
+            Optimized::Int(_, value) => Some(*value),
+            Optimized::Expr(..) => None,
+        }
+    }
+
+    fn can_fail(&self) -> bool {
+        match self {
+            Optimized::Int(..) => false,
+            Optimized::Expr(_, can_fail) => *can_fail,
+        }
+    }
+}
+
+impl Optimizer<'_> {
+    pub fn new(ast: &Ast) -> Optimizer<'_> {
+        Optimizer {
+            ast,
+            optimized_ast: Ast::new(),
+        }
+    }
+
+    pub fn optimize(mut self) -> Ast {
+        let root = self.optimize_expr(self.ast.root());
+        self.add(root);
+
+        self.optimized_ast
+    }
+
+    fn optimize_expr(&mut self, id: ExprId) -> Optimized {
+        let ast = self.ast;
+        // Optimized expressions which are left, but whose parents aren't.
+        let mut optimized = vec![];
+        // Lengths of the optimized AST when entering the binary expressions
+        // which aren't left yet.
+        let mut starts = vec![];
+
+        for step in ast.walk(id) {
+            match step {
+                Step::Enter(id) => {
+                    if let Expr::Binary(_) = &ast[id] {
+                        starts.push(self.optimized_ast.len());
+                    }
+                }
+                Step::Leave(id) => {
+                    let expr = match &ast[id] {
+                        Expr::Int(expr) => self.optimize_int_expr(expr),
+                        Expr::Group(expr) => {
+                            let inner = pop(&mut optimized);
+                            self.optimize_group_expr(expr, inner)
+                        }
+                        Expr::Binary(expr) => {
+                            let right = pop(&mut optimized);
+                            let left = pop(&mut optimized);
+                            let start = pop(&mut starts);
+                            self.optimize_binary_expr(expr, start, left, right)
+                        }
+                    };
+
+                    optimized.push(expr);
+                }
+            }
+        }
+
+        pop(&mut optimized)
+    }
+
+    fn optimize_int_expr(&mut self, expr: &IntExpr) -> Optimized {
+        Optimized::Int(expr.span, expr.value)
+    }
+
+    fn optimize_group_expr(&mut self, _expr: &GroupExpr, inner: Optimized) -> Optimized {
+        inner
+    }
+
+    // Everything added to the optimized AST since `start` belongs to the
+    // operands.
+    fn optimize_binary_expr(
+        &mut self,
+        expr: &BinaryExpr,
+        start: usize,
+        left: Optimized,
+        right: Optimized,
+    ) -> Optimized {
+        // Folding uses the same arithmetic as the evaluator, so it never
+        // changes the result. Division by zero is left unfolded, so that the
+        // evaluator reports it with the original span.
+        if let (Some(left), Some(right)) = (left.int_value(), right.int_value())
+            && let Some(value) = expr.op.apply(left, right)
+        {
+            return Optimized::Int(expr.span, value);
+        }
+
+        match (expr.op, left.int_value(), right.int_value()) {
+            (BinaryOp::Add, _, Some(0)) => left,
+            (BinaryOp::Add, Some(0), _) => right,
+            (BinaryOp::Sub, _, Some(0)) => left,
+            (BinaryOp::Mul, _, Some(1)) => left,
+            (BinaryOp::Mul, Some(1), _) => right,
+            (BinaryOp::Div, _, Some(1)) => left,
+
+            // Dropping the other operand is only safe when evaluating it can't
+            // fail, otherwise we would hide an error. The operand is the only
+            // thing added since `start`, so we can remove it.
+            (BinaryOp::Mul, _, Some(0)) if !left.can_fail() => {
+                self.optimized_ast.truncate(start);
+                Optimized::Int(expr.span, 0)
+            }
+            (BinaryOp::Mul, Some(0), _) if !right.can_fail() => {
+                self.optimized_ast.truncate(start);
+                Optimized::Int(expr.span, 0)
+            }
+
+            // A division which isn't folded may divide by zero.
+            _ => {
+                let can_fail = expr.op == BinaryOp::Div || left.can_fail() || right.can_fail();
+                let left = self.add(left);
+                let right = self.add(right);
+
+                Optimized::Expr(
+                    self.optimized_ast.binary(expr.span, expr.op, left, right),
+                    can_fail,
+                )
+            }
+        }
+    }
+
+    fn add(&mut self, optimized: Optimized) -> ExprId {
+        match optimized {
+            Optimized::Int(span, value) => self.optimized_ast.int(span, value),
+            Optimized::Expr(id, _) => id,
+        }
+    }
+}
+
+fn pop<T>(stack: &mut Vec<T>) -> T {
+    stack.pop().expect("child expressions are optimized")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::build::{Builder, ast, binary, group, int};
+
+    macro_rules! assert_optimizes {
+        ($ast:expr, $optimized_ast:expr $(,)?) => {
+            let optimized_ast = ast($optimized_ast);
+
+            let ast = ast($ast);
+            let optimizer = Optimizer::new(&ast);
+
+            assert_eq!(optimizer.optimize(), optimized_ast);
+        };
+    }
+
+    // The `1 / 0` expression can't be folded, so it is used as an operand that
+    // stays in the optimized tree.
+    fn div_by_zero(start: usize) -> Builder {
+        binary(
+            Span::new(start, start + 5),
+            BinaryOp::Div,
+            int(Span::new(start, start + 1), 1),
+            int(Span::new(start + 4, start + 5), 0),
+        )
+    }
+
+    #[test]
+    fn optimizes_int_expr() {
+        assert_optimizes!(int(Span::new(0, 1), 1), int(Span::new(0, 1), 1));
+    }
+
+    #[test]
+    fn optimizes_group_expr() {
+        assert_optimizes!(
+            group(Span::new(0, 3), int(Span::new(1, 2), 1)),
+            int(Span::new(1, 2), 1),
+        );
+        assert_optimizes!(group(Span::new(0, 7), div_by_zero(1)), div_by_zero(1));
+    }
+
+    #[test]
+    fn folds_binary_expr() {
+        assert_optimizes!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Add,
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
+            ),
+            int(Span::new(0, 5), 3),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Sub,
+                int(Span::new(0, 1), 3),
+                int(Span::new(4, 5), 2),
+            ),
+            int(Span::new(0, 5), 1),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Mul,
+                int(Span::new(0, 1), 2),
+                int(Span::new(4, 5), 3),
+            ),
+            int(Span::new(0, 5), 6),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 5),
+                BinaryOp::Div,
+                int(Span::new(0, 1), 6),
+                int(Span::new(4, 5), 3),
+            ),
+            int(Span::new(0, 5), 2),
+        );
+
+        // overflow
+        assert_optimizes!(
+            binary(
+                Span::new(0, 14),
+                BinaryOp::Add,
+                int(Span::new(0, 10), 2147483647),
+                int(Span::new(13, 14), 1),
+            ),
+            int(Span::new(0, 14), -2147483648),
+        );
+
+        // division by zero
+        assert_optimizes!(div_by_zero(0), div_by_zero(0));
+    }
+
+    #[test]
+    fn simplifies_binary_expr_add() {
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Add,
+                div_by_zero(0),
+                int(Span::new(8, 9), 0),
+            ),
+            div_by_zero(0),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Add,
+                int(Span::new(0, 1), 0),
+                div_by_zero(4),
+            ),
+            div_by_zero(4),
+        );
+    }
+
+    #[test]
+    fn simplifies_binary_expr_sub() {
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Sub,
+                div_by_zero(0),
+                int(Span::new(8, 9), 0),
+            ),
+            div_by_zero(0),
+        );
+
+        // `0 - x` is not `x`
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Sub,
+                int(Span::new(0, 1), 0),
+                div_by_zero(4),
+            ),
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Sub,
+                int(Span::new(0, 1), 0),
+                div_by_zero(4),
+            ),
+        );
+    }
+
+    #[test]
+    fn simplifies_binary_expr_mul() {
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Mul,
+                div_by_zero(0),
+                int(Span::new(8, 9), 1),
+            ),
+            div_by_zero(0),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Mul,
+                int(Span::new(0, 1), 1),
+                div_by_zero(4),
+            ),
+            div_by_zero(4),
+        );
+
+        // `x * 0` would hide the division by zero
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Mul,
+                div_by_zero(0),
+                int(Span::new(8, 9), 0),
+            ),
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Mul,
+                div_by_zero(0),
+                int(Span::new(8, 9), 0),
+            ),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Mul,
+                int(Span::new(0, 1), 0),
+                div_by_zero(4),
+            ),
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Mul,
+                int(Span::new(0, 1), 0),
+                div_by_zero(4),
+            ),
+        );
+    }
+
+    #[test]
+    fn simplifies_binary_expr_div() {
+        assert_optimizes!(
+            binary(
+                Span::new(0, 9),
+                BinaryOp::Div,
+                div_by_zero(0),
+                int(Span::new(8, 9), 1),
+            ),
+            div_by_zero(0),
+        );
+    }
+
+    #[test]
+    fn optimizes_complex_expressions() {
+        assert_optimizes!(
+            binary(
+                Span::new(0, 17),
+                BinaryOp::Mul,
+                group(
+                    Span::new(0, 7),
+                    binary(
+                        Span::new(1, 6),
+                        BinaryOp::Add,
+                        int(Span::new(1, 2), 1),
+                        int(Span::new(5, 6), 2),
+                    ),
+                ),
+                group(
+                    Span::new(10, 17),
+                    binary(
+                        Span::new(11, 16),
+                        BinaryOp::Add,
+                        int(Span::new(11, 12), 3),
+                        int(Span::new(15, 16), 4),
+                    ),
+                ),
+            ),
+            int(Span::new(0, 17), 21),
+        );
+        assert_optimizes!(
+            binary(
+                Span::new(0, 17),
+                BinaryOp::Add,
+                group(Span::new(0, 7), div_by_zero(1)),
+                group(
+                    Span::new(10, 17),
+                    binary(
+                        Span::new(11, 16),
+                        BinaryOp::Sub,
+                        int(Span::new(11, 12), 2),
+                        int(Span::new(15, 16), 2),
+                    ),
+                ),
+            ),
+            div_by_zero(1),
+        );
+    }
+}
@@ -1,78 +1,102 @@
-use std::cell::RefCell;
-use std::mem;
-use std::rc::Rc;
+use alloc::format;
+use alloc::string::String;
+use core::mem;
 
-use crate::ast::{BinaryOp, Expr};
+use crate::ast::{Ast, BinaryOp, ExprId};
 use crate::error::Error;
+use crate::limits::{Limit, Limits};
 use crate::scanner::Scanner;
-use crate::source::{SourceMap, SourceSpan, Span, Spanned};
+use crate::source::{SourceMap, Span, Spanned};
 use crate::token::{Token, TokenKind};
 
 pub struct Parser<'a> {
     scanner: Scanner<'a>,
-    source_map: Rc<RefCell<SourceMap>>,
+    limits: Limits,
     current: Token,
+    ast: Ast,
+    nesting: usize,
 }
 
 impl Parser<'_> {
-    pub fn new(input: &str, source_map: Rc<RefCell<SourceMap>>) -> Parser {
+    pub fn new(input: &str, source_map: SourceMap, limits: Limits) -> Parser<'_> {
         Parser {
-            scanner: Scanner::new(input, Rc::clone(&source_map)),
-            source_map,
+            scanner: Scanner::new(input, source_map, limits),
+            limits,
             current: Token::eof(Span::new(0, 0)),
+            ast: Ast::new(),
+            nesting: 0,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Box<Expr>, Error> {
-        self.advance();
+    // Makes the parser add expressions to an existing AST, which is cleared
+    // first, so that its memory is reused.
+    pub fn with_ast(mut self, mut ast: Ast) -> Self {
+        ast.truncate(0);
+        self.ast = ast;
+        self
+    }
+
+    pub fn parse(self) -> Result<Ast, Error> {
+        self.parse_with_source_map().map(|(ast, _)| ast)
+    }
+
+    // Parses the input and returns the AST together with the source map filled
+    // in by the scanner, which is needed to map spans of the AST.
+    pub fn parse_with_source_map(mut self) -> Result<(Ast, SourceMap), Error> {
+        self.advance()?;
 
-        let expr = self.parse_expr()?;
+        self.parse_expr()?;
         self.expect(TokenKind::Eof)?;
 
-        Ok(expr)
+        Ok((self.ast, self.scanner.into_source_map()))
     }
 
-    fn parse_expr(&mut self) -> Result<Box<Expr>, Error> {
+    fn parse_expr(&mut self) -> Result<ExprId, Error> {
         let mut left = self.parse_term()?;
 
-        while let Some(op) = self.accept_any(&[TokenKind::Plus, TokenKind::Minus]) {
+        while let Some(op) = self.accept_any(&[TokenKind::Plus, TokenKind::Minus])? {
             let right = self.parse_term()?;
-            let span = Span::cover(left.span(), right.span());
 
-            left = Expr::binary(span, BinaryOp::from_token(op), left, right);
+            left = self.add_binary(op, left, right)?;
         }
 
         Ok(left)
     }
 
-    fn parse_term(&mut self) -> Result<Box<Expr>, Error> {
+    fn parse_term(&mut self) -> Result<ExprId, Error> {
         let mut left = self.parse_factor()?;
 
-        while let Some(op) = self.accept_any(&[TokenKind::Star, TokenKind::Slash]) {
+        while let Some(op) = self.accept_any(&[TokenKind::Star, TokenKind::Slash])? {
             let right = self.parse_factor()?;
-            let span = Span::cover(left.span(), right.span());
 
-            left = Expr::binary(span, BinaryOp::from_token(op), left, right);
+            left = self.add_binary(op, left, right)?;
         }
 
         Ok(left)
     }
 
-    fn parse_factor(&mut self) -> Result<Box<Expr>, Error> {
+    fn parse_factor(&mut self) -> Result<ExprId, Error> {
         match self.current().kind() {
             TokenKind::Int => {
-                let int = self.advance();
+                let int = self.advance()?;
 
-                Ok(Expr::int(int.span(), int.int_value()))
+                self.add_int(int)
             }
 
             TokenKind::LParen => {
-                let l_paren = self.advance();
+                let l_paren = self.advance()?;
+
+                // Check the nesting before recursing, so that deeply nested
+                // input doesn't overflow the stack.
+                self.nesting += 1;
+                self.check_depth(&l_paren, self.nesting)?;
+
                 let expr = self.parse_expr()?;
                 let r_paren = self.expect(TokenKind::RParen)?;
-                let span = Span::cover(l_paren.span(), r_paren.span());
 
-                Ok(Expr::group(span, expr))
+                self.nesting -= 1;
+
+                self.add_group(l_paren, expr, r_paren)
             }
 
             _ => Err(self.error(
@@ -86,24 +110,66 @@ impl Parser<'_> {
         }
     }
 
-    fn accept_any(&mut self, kinds: &[TokenKind]) -> Option<Token> {
+    fn add_int(&mut self, int: Token) -> Result<ExprId, Error> {
+        self.check_nodes(&int)?;
+
+        Ok(self.ast.int(int.span(), int.int_value()))
+    }
+
+    fn add_group(&mut self, l_paren: Token, expr: ExprId, r_paren: Token) -> Result<ExprId, Error> {
+        self.check_nodes(&l_paren)?;
+
+        let span = Span::cover(l_paren.span(), r_paren.span());
+
+        Ok(self.ast.group(span, expr))
+    }
+
+    fn add_binary(&mut self, op: Token, left: ExprId, right: ExprId) -> Result<ExprId, Error> {
+        self.check_nodes(&op)?;
+
+        let span = Span::cover(self.ast[left].span(), self.ast[right].span());
+
+        Ok(self.ast.binary(span, BinaryOp::from_token(op), left, right))
+    }
+
+    // The parser recurses into parentheses, so their nesting is always limited
+    // to prevent stack overflows. Chains of operators are parsed in loops.
+    fn check_depth(&self, spanned: &impl Spanned, depth: usize) -> Result<(), Error> {
+        if depth > self.limits.max_depth {
+            return Err(self.limit_error(spanned, Limit::Depth));
+        }
+
+        Ok(())
+    }
+
+    fn check_nodes(&self, spanned: &impl Spanned) -> Result<(), Error> {
+        if self.ast.len() >= self.limits.max_nodes {
+            return Err(self.limit_error(spanned, Limit::Nodes));
+        }
+
+        Ok(())
+    }
+
+    fn accept_any(&mut self, kinds: &[TokenKind]) -> Result<Option<Token>, Error> {
         if kinds.contains(&self.current().kind()) {
-            Some(self.advance())
+            Ok(Some(self.advance()?))
         } else {
-            None
+            Ok(None)
         }
     }
 
     fn expect(&mut self, kind: TokenKind) -> Result<Token, Error> {
         if self.current().kind() == kind {
-            Ok(self.advance())
+            self.advance()
         } else {
             Err(self.error(self.current(), format!("expected {}", kind.as_str())))
         }
     }
 
-    fn advance(&mut self) -> Token {
-        mem::replace(&mut self.current, self.scanner.scan())
+    fn advance(&mut self) -> Result<Token, Error> {
+        let next = self.scanner.scan()?;
+
+        Ok(mem::replace(&mut self.current, next))
     }
 
     fn current(&self) -> &Token {
@@ -111,32 +177,33 @@ impl Parser<'_> {
     }
 
     fn error(&self, spanned: &impl Spanned, message: impl Into<String>) -> Error {
-        Error::new(self.map_span(spanned.span()), message)
+        self.scanner.error(spanned.span(), message)
     }
 
-    fn map_span(&self, span: Span) -> SourceSpan {
-        self.source_map.borrow().map_span(span)
+    fn limit_error(&self, spanned: &impl Spanned, limit: Limit) -> Error {
+        self.scanner.limit_error(spanned.span(), limit)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+
     use super::*;
+    use crate::ast::build::{ast, binary, group, int};
     use crate::{SourcePos, SourceSpan};
 
     macro_rules! assert_parses {
         ($input:expr, $ast:expr $(,)?) => {
-            let source_map = Rc::new(RefCell::new(SourceMap::new()));
-            let mut parser = Parser::new($input, Rc::clone(&source_map));
+            let parser = Parser::new($input, SourceMap::new(), Limits::default());
 
-            assert_eq!(parser.parse(), Ok($ast));
+            assert_eq!(parser.parse(), Ok(ast($ast)));
         };
     }
 
     macro_rules! assert_does_not_parse {
         ($input:expr, $error:expr $(,)?) => {
-            let source_map = Rc::new(RefCell::new(SourceMap::new()));
-            let mut parser = Parser::new($input, Rc::clone(&source_map));
+            let parser = Parser::new($input, SourceMap::new(), Limits::default());
 
             assert_eq!(parser.parse(), Err($error));
         };
@@ -147,87 +214,87 @@ mod tests {
     fn parses_expr() {
         assert_parses!(
             "1 * 2",
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Mul,
-                Expr::int(Span::new(0, 1), 1),
-                Expr::int(Span::new(4, 5), 2),
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
             ),
         );
         assert_parses!(
             "1 * 2 + 3 * 4",
-            Expr::binary(
+            binary(
                 Span::new(0, 13),
                 BinaryOp::Add,
-                Expr::binary(
+                binary(
                     Span::new(0, 5),
                     BinaryOp::Mul,
-                    Expr::int(Span::new(0, 1), 1),
-                    Expr::int(Span::new(4, 5), 2),
+                    int(Span::new(0, 1), 1),
+                    int(Span::new(4, 5), 2),
                 ),
-                Expr::binary(
+                binary(
                     Span::new(8, 13),
                     BinaryOp::Mul,
-                    Expr::int(Span::new(8, 9), 3),
-                    Expr::int(Span::new(12, 13), 4),
+                    int(Span::new(8, 9), 3),
+                    int(Span::new(12, 13), 4),
                 ),
             ),
         );
         assert_parses!(
             "1 * 2 - 3 * 4",
-            Expr::binary(
+            binary(
                 Span::new(0, 13),
                 BinaryOp::Sub,
-                Expr::binary(
+                binary(
                     Span::new(0, 5),
                     BinaryOp::Mul,
-                    Expr::int(Span::new(0, 1), 1),
-                    Expr::int(Span::new(4, 5), 2),
+                    int(Span::new(0, 1), 1),
+                    int(Span::new(4, 5), 2),
                 ),
-                Expr::binary(
+                binary(
                     Span::new(8, 13),
                     BinaryOp::Mul,
-                    Expr::int(Span::new(8, 9), 3),
-                    Expr::int(Span::new(12, 13), 4),
+                    int(Span::new(8, 9), 3),
+                    int(Span::new(12, 13), 4),
                 ),
             ),
         );
         assert_parses!(
             "1 * 2 + 3 * 4 + 5 * 6 + 7 * 8",
-            Expr::binary(
+            binary(
                 Span::new(0, 29),
                 BinaryOp::Add,
-                Expr::binary(
+                binary(
                     Span::new(0, 21),
                     BinaryOp::Add,
-                    Expr::binary(
+                    binary(
                         Span::new(0, 13),
                         BinaryOp::Add,
-                        Expr::binary(
+                        binary(
                             Span::new(0, 5),
                             BinaryOp::Mul,
-                            Expr::int(Span::new(0, 1), 1),
-                            Expr::int(Span::new(4, 5), 2),
+                            int(Span::new(0, 1), 1),
+                            int(Span::new(4, 5), 2),
                         ),
-                        Expr::binary(
+                        binary(
                             Span::new(8, 13),
                             BinaryOp::Mul,
-                            Expr::int(Span::new(8, 9), 3),
-                            Expr::int(Span::new(12, 13), 4),
+                            int(Span::new(8, 9), 3),
+                            int(Span::new(12, 13), 4),
                         ),
                     ),
-                    Expr::binary(
+                    binary(
                         Span::new(16, 21),
                         BinaryOp::Mul,
-                        Expr::int(Span::new(16, 17), 5),
-                        Expr::int(Span::new(20, 21), 6),
+                        int(Span::new(16, 17), 5),
+                        int(Span::new(20, 21), 6),
                     ),
                 ),
-                Expr::binary(
+                binary(
                     Span::new(24, 29),
                     BinaryOp::Mul,
-                    Expr::int(Span::new(24, 25), 7),
-                    Expr::int(Span::new(28, 29), 8),
+                    int(Span::new(24, 25), 7),
+                    int(Span::new(28, 29), 8),
                 ),
             ),
         );
@@ -266,42 +333,42 @@ mod tests {
     // Canonical term is `1 * 2`.
     #[test]
     fn parses_term() {
-        assert_parses!("1", Expr::int(Span::new(0, 1), 1));
+        assert_parses!("1", int(Span::new(0, 1), 1));
         assert_parses!(
             "1 * 2",
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Mul,
-                Expr::int(Span::new(0, 1), 1),
-                Expr::int(Span::new(4, 5), 2),
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
             ),
         );
         assert_parses!(
             "1 / 2",
-            Expr::binary(
+            binary(
                 Span::new(0, 5),
                 BinaryOp::Div,
-                Expr::int(Span::new(0, 1), 1),
-                Expr::int(Span::new(4, 5), 2),
+                int(Span::new(0, 1), 1),
+                int(Span::new(4, 5), 2),
             ),
         );
         assert_parses!(
             "1 * 2 * 3 * 4",
-            Expr::binary(
+            binary(
                 Span::new(0, 13),
                 BinaryOp::Mul,
-                Expr::binary(
+                binary(
                     Span::new(0, 9),
                     BinaryOp::Mul,
-                    Expr::binary(
+                    binary(
                         Span::new(0, 5),
                         BinaryOp::Mul,
-                        Expr::int(Span::new(0, 1), 1),
-                        Expr::int(Span::new(4, 5), 2),
+                        int(Span::new(0, 1), 1),
+                        int(Span::new(4, 5), 2),
                     ),
-                    Expr::int(Span::new(8, 9), 3),
+                    int(Span::new(8, 9), 3),
                 ),
-                Expr::int(Span::new(12, 13), 4),
+                int(Span::new(12, 13), 4),
             ),
         );
 
@@ -339,16 +406,16 @@ mod tests {
     // Canonical factor is `1`.
     #[test]
     fn parses_factor() {
-        assert_parses!("1", Expr::int(Span::new(0, 1), 1));
+        assert_parses!("1", int(Span::new(0, 1), 1));
         assert_parses!(
             "(1 + 2)",
-            Expr::group(
+            group(
                 Span::new(0, 7),
-                Expr::binary(
+                binary(
                     Span::new(1, 6),
                     BinaryOp::Add,
-                    Expr::int(Span::new(1, 2), 1),
-                    Expr::int(Span::new(5, 6), 2),
+                    int(Span::new(1, 2), 1),
+                    int(Span::new(5, 6), 2),
                 ),
             ),
         );
@@ -388,25 +455,25 @@ mod tests {
     fn parses_complex_expressions() {
         assert_parses!(
             "(1 + 2) * (3 + 4)",
-            Expr::binary(
+            binary(
                 Span::new(0, 17),
                 BinaryOp::Mul,
-                Expr::group(
+                group(
                     Span::new(0, 7),
-                    Expr::binary(
+                    binary(
                         Span::new(1, 6),
                         BinaryOp::Add,
-                        Expr::int(Span::new(1, 2), 1),
-                        Expr::int(Span::new(5, 6), 2),
+                        int(Span::new(1, 2), 1),
+                        int(Span::new(5, 6), 2),
                     ),
                 ),
-                Expr::group(
+                group(
                     Span::new(10, 17),
-                    Expr::binary(
+                    binary(
                         Span::new(11, 16),
                         BinaryOp::Add,
-                        Expr::int(Span::new(11, 12), 3),
-                        Expr::int(Span::new(15, 16), 4),
+                        int(Span::new(11, 12), 3),
+                        int(Span::new(15, 16), 4),
                     ),
                 ),
             ),
@@ -424,6 +491,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn does_not_parse_deeply_nested_input() {
+        let parens = "(".repeat(100000);
+        let group = format!("{}1{}", "(".repeat(257), ")".repeat(257));
+
+        assert_does_not_parse!(
+            &parens,
+            Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(256, 1, 257), SourcePos::new(257, 1, 258)),
+                Limit::Depth,
+            ),
+        );
+        assert_does_not_parse!(
+            &group,
+            Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(256, 1, 257), SourcePos::new(257, 1, 258)),
+                Limit::Depth,
+            ),
+        );
+    }
+
+    #[test]
+    fn parses_nested_input_up_to_max_depth() {
+        let input = format!("{}1{}", "(".repeat(256), ")".repeat(256));
+        let parser = Parser::new(&input, SourceMap::new(), Limits::default());
+        assert!(parser.parse().is_ok());
+    }
+
+    #[test]
+    fn parses_long_chains_of_operators() {
+        let sum = vec!["1"; 100000].join(" + ");
+        let parser = Parser::new(&sum, SourceMap::new(), Limits::default());
+        assert!(parser.parse().is_ok());
+
+        let product = vec!["1"; 100000].join(" * ");
+        let parser = Parser::new(&product, SourceMap::new(), Limits::default());
+        assert!(parser.parse().is_ok());
+    }
+
+    #[test]
+    fn enforces_depth_limit() {
+        let limits = Limits {
+            max_depth: 3,
+            ..Limits::default()
+        };
+
+        let parser = Parser::new("(((1 + 2 + 3)))", SourceMap::new(), limits);
+        assert!(parser.parse().is_ok());
+
+        let parser = Parser::new("((((1))))", SourceMap::new(), limits);
+        assert_eq!(
+            parser.parse(),
+            Err(Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(3, 1, 4), SourcePos::new(4, 1, 5)),
+                Limit::Depth,
+            )),
+        );
+    }
+
+    #[test]
+    fn enforces_nodes_limit() {
+        let limits = Limits {
+            max_nodes: 3,
+            ..Limits::default()
+        };
+
+        let parser = Parser::new("1 + 2", SourceMap::new(), limits);
+        assert!(parser.parse().is_ok());
+
+        let parser = Parser::new("(1 + 2)", SourceMap::new(), limits);
+        assert_eq!(
+            parser.parse(),
+            Err(Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(1, 1, 2)),
+                Limit::Nodes,
+            )),
+        );
+
+        let parser = Parser::new("1 + 2 + 3", SourceMap::new(), limits);
+        assert_eq!(
+            parser.parse(),
+            Err(Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(8, 1, 9), SourcePos::new(9, 1, 10)),
+                Limit::Nodes,
+            )),
+        );
+    }
+
     #[test]
     fn does_not_parse_trailing_input() {
         assert_does_not_parse!(
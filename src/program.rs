@@ -0,0 +1,19 @@
+use alloc::vec::Vec;
+
+use crate::ast::BinaryOp;
+use crate::source::Span;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Instruction {
+    Int(i32),
+    Binary(BinaryOp),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    // Span of the expression each instruction was compiled from, used to report
+    // errors. Indexed in parallel with `instructions`.
+    pub spans: Vec<Span>,
+    pub stack_size: usize,
+}
@@ -1,30 +1,101 @@
-use std::cell::RefCell;
-use std::iter::Peekable;
-use std::rc::Rc;
-use std::str::Chars;
+use alloc::string::String;
+use core::iter::Peekable;
+use core::str::Chars;
 
-use crate::source::{SourceMap, Span};
-use crate::token::Token;
+use crate::error::Error;
+use crate::limits::{Limit, Limits};
+use crate::source::{SourceMap, SourceSpan, Span, Spanned};
+use crate::token::{Token, TokenKind};
 
 pub struct Scanner<'a> {
+    input: &'a str,
     chars: Peekable<Chars<'a>>,
-    source_map: Rc<RefCell<SourceMap>>,
+    source_map: Option<SourceMap>,
+    limits: Limits,
+    token_count: usize,
+    keep_trivia: bool,
+    unterminated_comment: Option<Span>,
     pos: usize,
     start_pos: usize,
 }
 
 impl Scanner<'_> {
-    pub fn new(input: &str, source_map: Rc<RefCell<SourceMap>>) -> Scanner {
+    pub fn new(input: &str, source_map: SourceMap, limits: Limits) -> Scanner<'_> {
         Scanner {
+            source_map: Some(source_map),
+            ..Scanner::without_source_map(input, limits)
+        }
+    }
+
+    // Creates a scanner that doesn't fill in a source map, so that scanning
+    // doesn't allocate. Spans are mapped by building the source map from the
+    // whole input, which is slower, but only needed for errors.
+    pub fn without_source_map(input: &str, limits: Limits) -> Scanner<'_> {
+        Scanner {
+            input,
             chars: input.chars().peekable(),
-            source_map,
+            source_map: None,
+            limits,
+            token_count: 0,
+            keep_trivia: false,
+            unterminated_comment: None,
             pos: 0,
             start_pos: 0,
         }
     }
 
-    pub fn scan(&mut self) -> Token {
-        self.skip_whitespace();
+    // Makes the scanner produce whitespace, newline, and comment tokens instead
+    // of skipping them. This is used to build the concrete syntax tree.
+    pub fn keep_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    // The source map is filled in as the input is scanned, so spans of all
+    // tokens scanned so far can be mapped.
+    pub fn map_span(&self, span: Span) -> SourceSpan {
+        self.with_source_map(|source_map| source_map.map_span(span))
+    }
+
+    pub fn into_source_map(self) -> SourceMap {
+        self.source_map
+            .unwrap_or_else(|| SourceMap::from_input(self.input))
+    }
+
+    pub fn scan(&mut self) -> Result<Token, Error> {
+        // The length is checked before scanning anything, so that long input
+        // is rejected quickly.
+        if self.input.len() > self.limits.max_input_len {
+            return Err(self.limit_error(Span::new(0, 0), Limit::InputLen));
+        }
+
+        let token = self.scan_token();
+
+        if let Some(span) = self.unterminated_comment.take() {
+            return Err(self.error(span, "unterminated block comment"));
+        }
+
+        if token.kind() != TokenKind::Eof {
+            self.token_count += 1;
+            if self.token_count > self.limits.max_tokens {
+                return Err(self.limit_error(token.span(), Limit::Tokens));
+            }
+        }
+
+        Ok(token)
+    }
+
+    pub fn scan_token(&mut self) -> Token {
+        loop {
+            let token = self.scan_any_token();
+
+            if self.keep_trivia || !token.kind().is_trivia() {
+                return token;
+            }
+        }
+    }
+
+    fn scan_any_token(&mut self) -> Token {
         self.start();
 
         let Some(ch) = self.next() else {
@@ -32,10 +103,13 @@ impl Scanner<'_> {
         };
 
         match ch {
+            '\n' => Token::newline(self.span()),
+            _ if is_whitespace(ch) => self.scan_whitespace_rest(),
+
             '+' => Token::plus(self.span()),
             '-' => Token::minus(self.span()),
             '*' => Token::star(self.span()),
-            '/' => Token::slash(self.span()),
+            '/' => self.scan_slash_rest(),
             '(' => Token::l_paren(self.span()),
             ')' => Token::r_paren(self.span()),
 
@@ -45,12 +119,53 @@ impl Scanner<'_> {
         }
     }
 
-    fn skip_whitespace(&mut self) {
+    fn scan_whitespace_rest(&mut self) -> Token {
         while let Some(&ch) = self.peek()
             && is_whitespace(ch)
+            && ch != '\n'
         {
             self.next();
         }
+
+        Token::whitespace(self.span())
+    }
+
+    fn scan_slash_rest(&mut self) -> Token {
+        match self.peek() {
+            Some('/') => self.scan_line_comment_rest(),
+            Some('*') => self.scan_block_comment_rest(),
+            _ => Token::slash(self.span()),
+        }
+    }
+
+    fn scan_line_comment_rest(&mut self) -> Token {
+        while let Some(&ch) = self.peek()
+            && ch != '\n'
+        {
+            self.next();
+        }
+
+        Token::line_comment(self.span())
+    }
+
+    fn scan_block_comment_rest(&mut self) -> Token {
+        self.next();
+
+        loop {
+            match self.next() {
+                Some('*') if self.peek() == Some(&'/') => {
+                    self.next();
+                    break;
+                }
+                Some(_) => {}
+                None => {
+                    self.unterminated_comment = Some(self.span());
+                    break;
+                }
+            }
+        }
+
+        Token::block_comment(self.span())
     }
 
     fn scan_int_rest(&mut self, first_ch: char) -> Token {
@@ -77,21 +192,36 @@ impl Scanner<'_> {
 
     fn next(&mut self) -> Option<char> {
         self.chars.next().inspect(|&ch| {
-            self.pos += 1;
+            if let Some(source_map) = &mut self.source_map {
+                source_map.add_char(self.pos, ch);
 
-            if ch == '\n' {
-                self.add_line_start(self.pos);
+                if ch == '\n' {
+                    source_map.add_line_start(self.pos + 1);
+                }
             }
-        })
-    }
 
-    fn add_line_start(&self, pos: usize) {
-        self.source_map.borrow_mut().add_line_start(pos)
+            self.pos += 1;
+        })
     }
 
     fn span(&mut self) -> Span {
         Span::new(self.start_pos, self.pos)
     }
+
+    pub fn error(&self, span: Span, message: impl Into<String>) -> Error {
+        self.with_source_map(|source_map| source_map.error(span, message))
+    }
+
+    pub fn limit_error(&self, span: Span, limit: Limit) -> Error {
+        self.with_source_map(|source_map| source_map.limit_error(span, limit))
+    }
+
+    fn with_source_map<T>(&self, f: impl FnOnce(&SourceMap) -> T) -> T {
+        match &self.source_map {
+            Some(source_map) => f(source_map),
+            None => f(&SourceMap::from_input(self.input)),
+        }
+    }
 }
 
 fn is_whitespace(ch: char) -> bool {
@@ -108,17 +238,17 @@ fn to_digit(ch: char) -> i32 {
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+
     use super::*;
     use crate::source::{SourcePos, SourceSpan};
-    use crate::token::TokenKind;
 
     macro_rules! assert_scans {
         ($input:expr, $tokens:expr $(,)?) => {
-            let source_map = Rc::new(RefCell::new(SourceMap::new()));
-            let mut scanner = Scanner::new($input, Rc::clone(&source_map));
+            let mut scanner = Scanner::new($input, SourceMap::new(), Limits::default());
 
             let mut tokens = vec![];
-            while let token = scanner.scan()
+            while let token = scanner.scan().unwrap()
                 && token.kind() != TokenKind::Eof
             {
                 tokens.push(token);
@@ -194,14 +324,166 @@ mod tests {
         );
     }
 
+    #[test]
+    fn scans_trivia_tokens() {
+        let mut scanner =
+            Scanner::new(" 1\t\r\n\n+", SourceMap::new(), Limits::default()).keep_trivia();
+
+        let mut tokens = vec![];
+        while let token = scanner.scan_token()
+            && token.kind() != TokenKind::Eof
+        {
+            tokens.push(token);
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                Token::whitespace(Span::new(0, 1)),
+                Token::int(Span::new(1, 2), 1),
+                Token::whitespace(Span::new(2, 4)),
+                Token::newline(Span::new(4, 5)),
+                Token::newline(Span::new(5, 6)),
+                Token::plus(Span::new(6, 7)),
+            ],
+        );
+
+        let mut scanner =
+            Scanner::new("// a\n/* b */", SourceMap::new(), Limits::default()).keep_trivia();
+
+        assert_eq!(scanner.scan_token(), Token::line_comment(Span::new(0, 4)));
+        assert_eq!(scanner.scan_token(), Token::newline(Span::new(4, 5)));
+        assert_eq!(scanner.scan_token(), Token::block_comment(Span::new(5, 12)));
+        assert_eq!(scanner.scan_token(), Token::eof(Span::new(12, 12)));
+    }
+
+    #[test]
+    fn skips_comments() {
+        // line comments
+        assert_scans!("1 // comment", vec![Token::int(Span::new(0, 1), 1)]);
+        assert_scans!(
+            "1 // comment\n+",
+            vec![
+                Token::int(Span::new(0, 1), 1),
+                Token::plus(Span::new(13, 14)),
+            ],
+        );
+        assert_scans!("//", vec![]);
+
+        // block comments
+        assert_scans!("1 /* comment */", vec![Token::int(Span::new(0, 1), 1)]);
+        assert_scans!(
+            "1 /* multi\nline */ +",
+            vec![
+                Token::int(Span::new(0, 1), 1),
+                Token::plus(Span::new(19, 20)),
+            ],
+        );
+        assert_scans!("/**/", vec![]);
+        assert_scans!("/***/", vec![]);
+        assert_scans!("/* /* */", vec![]);
+
+        // slashes
+        assert_scans!(
+            "1 / / 2",
+            vec![
+                Token::int(Span::new(0, 1), 1),
+                Token::slash(Span::new(2, 3)),
+                Token::slash(Span::new(4, 5)),
+                Token::int(Span::new(6, 7), 2),
+            ],
+        );
+        assert_scans!(
+            "1/2",
+            vec![
+                Token::int(Span::new(0, 1), 1),
+                Token::slash(Span::new(1, 2)),
+                Token::int(Span::new(2, 3), 2),
+            ],
+        );
+    }
+
+    #[test]
+    fn does_not_scan_unterminated_block_comment() {
+        let mut scanner = Scanner::new("1 /* a\nb *", SourceMap::new(), Limits::default());
+
+        assert_eq!(scanner.scan(), Ok(Token::int(Span::new(0, 1), 1)));
+        assert_eq!(
+            scanner.scan(),
+            Err(Error::new(
+                SourceSpan::new(SourcePos::new(2, 1, 3), SourcePos::new(10, 2, 4)),
+                "unterminated block comment",
+            )),
+        );
+    }
+
+    #[test]
+    fn enforces_input_len_limit() {
+        let limits = Limits {
+            max_input_len: 3,
+            ..Limits::default()
+        };
+
+        let mut scanner = Scanner::new("1+2", SourceMap::new(), limits);
+        assert!(scanner.scan().is_ok());
+
+        let mut scanner = Scanner::new("1+23", SourceMap::new(), limits);
+        assert_eq!(
+            scanner.scan(),
+            Err(Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(0, 1, 1)),
+                Limit::InputLen,
+            )),
+        );
+
+        // the length is in bytes
+        let mut scanner = Scanner::new("‰", SourceMap::new(), limits);
+        assert!(scanner.scan().is_ok());
+
+        let mut scanner = Scanner::new("‰ ", SourceMap::new(), limits);
+        assert!(scanner.scan().is_err());
+    }
+
+    #[test]
+    fn enforces_tokens_limit() {
+        let limits = Limits {
+            max_tokens: 3,
+            ..Limits::default()
+        };
+
+        let mut scanner = Scanner::new("1 + 2 + 3", SourceMap::new(), limits);
+
+        assert_eq!(scanner.scan(), Ok(Token::int(Span::new(0, 1), 1)));
+        assert_eq!(scanner.scan(), Ok(Token::plus(Span::new(2, 3))));
+        assert_eq!(scanner.scan(), Ok(Token::int(Span::new(4, 5), 2)));
+        assert_eq!(
+            scanner.scan(),
+            Err(Error::limit_exceeded(
+                SourceSpan::new(SourcePos::new(6, 1, 7), SourcePos::new(7, 1, 8)),
+                Limit::Tokens,
+            )),
+        );
+
+        // end of input doesn't count
+        let mut scanner = Scanner::new("1 + 2", SourceMap::new(), limits);
+
+        for _ in 0..3 {
+            assert!(scanner.scan().is_ok());
+        }
+        assert_eq!(scanner.scan(), Ok(Token::eof(Span::new(5, 5))));
+    }
+
     #[test]
     fn updates_source_map() {
-        let source_map = Rc::new(RefCell::new(SourceMap::new()));
-        let mut scanner = Scanner::new("1 +\n2 +\n3", Rc::clone(&source_map));
+        let mut scanner = Scanner::new(
+            "1 + // a\n2 + /* b\n*/3",
+            SourceMap::new(),
+            Limits::default(),
+        );
 
-        while scanner.scan().kind() != TokenKind::Eof {}
+        while scanner.scan().unwrap().kind() != TokenKind::Eof {}
 
-        let source_map = source_map.borrow();
+        let source_map = scanner.into_source_map();
 
         // line 1
         assert_eq!(
@@ -211,14 +493,14 @@ mod tests {
 
         // line 2
         assert_eq!(
-            source_map.map_span(Span::new(4, 5)),
-            SourceSpan::new(SourcePos::new(4, 2, 1), SourcePos::new(5, 2, 2))
+            source_map.map_span(Span::new(9, 10)),
+            SourceSpan::new(SourcePos::new(9, 2, 1), SourcePos::new(10, 2, 2))
         );
 
         // line 3
         assert_eq!(
-            source_map.map_span(Span::new(8, 9)),
-            SourceSpan::new(SourcePos::new(8, 3, 1), SourcePos::new(9, 3, 2))
+            source_map.map_span(Span::new(20, 21)),
+            SourceSpan::new(SourcePos::new(20, 3, 3), SourcePos::new(21, 3, 4))
         );
     }
 }
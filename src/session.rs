@@ -0,0 +1,136 @@
+use crate::ast::Ast;
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::evaluator::Evaluator;
+use crate::parser::Parser;
+use crate::source::SourceMap;
+
+/// Session for evaluating many expressions with an [`Engine`], reusing memory
+/// between them.
+///
+/// A session is created by [`Engine::session`]. It keeps the arena of the
+/// abstract syntax tree and the line table of the source map from one
+/// evaluation to the next, so evaluating an expression allocates only when
+/// the buffers have to grow. Unlike the engine, a session is used by one caller
+/// at a time, because evaluating needs `&mut` access to it. Give each thread
+/// its own session.
+///
+/// The results are the same as the results of [`Engine::eval`].
+///
+/// # Examples
+///
+/// ```
+/// use sari::Engine;
+///
+/// let engine = Engine::new();
+/// let mut session = engine.session();
+///
+/// for (expr, result) in [("1 + 2", 3), ("(1 + 2) * 3", 9), ("8 / 2 / 2", 2)] {
+///     assert_eq!(session.eval(expr), Ok(result));
+/// }
+/// assert_eq!(session.eval("1 / 0").unwrap_err().message(), "division by zero");
+/// ```
+#[derive(Debug)]
+pub struct Session {
+    engine: Engine,
+    // Buffers of the last successful evaluation.
+    buffers: Option<(Ast, SourceMap)>,
+}
+
+impl Session {
+    pub(crate) fn new(engine: Engine) -> Session {
+        Session {
+            engine,
+            buffers: None,
+        }
+    }
+
+    /// Returns the engine the session was created by.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Evaluates an expression and returns the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the evaluation fails or a limit is exceeded.
+    pub fn eval(&mut self, expr: &str) -> Result<i32, Error> {
+        let limits = self.engine.limits();
+
+        // The parser takes the buffers and gives them back on success. After a
+        // parsing error, the next evaluation starts with new buffers.
+        let (ast, mut source_map) = self
+            .buffers
+            .take()
+            .unwrap_or_else(|| (Ast::new(), SourceMap::new()));
+        source_map.clear();
+
+        let (ast, source_map) = Parser::new(expr, source_map, limits)
+            .with_ast(ast)
+            .parse_with_source_map()?;
+
+        let result = Evaluator::new(&ast, &source_map, limits).eval();
+        self.buffers = Some((ast, source_map));
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{Limit, Limits};
+
+    #[test]
+    fn evals_like_engine() {
+        let engine = Engine::new();
+        let mut session = engine.session();
+
+        let inputs = [
+            "1 + 2 * 3",
+            "1 +\n(2 / 0)",
+            "1 / 0",
+            "(1 + 2",
+            "(1 + 2) * 3",
+            "// ‰\n1 /\n0",
+            "2147483647 + 1",
+        ];
+
+        // Twice, so that each input is evaluated with buffers left over from
+        // another input.
+        for input in inputs.iter().chain(&inputs) {
+            assert_eq!(session.eval(input), engine.eval(input), "input: {input:?}");
+        }
+    }
+
+    #[test]
+    fn reuses_buffers() {
+        let mut session = Engine::new().session();
+
+        assert_eq!(session.eval("1 + 2"), Ok(3));
+        assert!(session.buffers.is_some());
+
+        // parsing errors drop the buffers
+        assert!(session.eval("1 +").is_err());
+        assert!(session.buffers.is_none());
+
+        assert_eq!(session.eval("3"), Ok(3));
+        assert!(session.buffers.is_some());
+    }
+
+    #[test]
+    fn applies_limits() {
+        let engine = Engine::new().with_limits(Limits {
+            max_steps: 3,
+            ..Limits::default()
+        });
+        let mut session = engine.session();
+
+        assert_eq!(session.eval("1 + 2"), Ok(3));
+        assert_eq!(
+            session.eval("(1 + 2)").unwrap_err().limit(),
+            Some(Limit::Steps),
+        );
+    }
+}
@@ -1,19 +1,57 @@
-use std::cmp::Ordering;
-use std::fmt;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+use crate::error::Error;
+use crate::limits::Limit;
+use crate::source_db::SourceId;
+
+/// Span in source code, represented by character offsets.
+///
+/// This is a compact form of [`SourceSpan`] used in the
+/// [AST](crate::ast). It can be converted to a `SourceSpan` using
+/// [`Expression::source_span`](crate::Expression::source_span).
+///
+/// The start offset is inclusive, the end offset is exclusive.
+///
+/// # Example
+///
+/// ```
+/// use sari::Span;
+///
+/// let span = Span::new(69, 74);
+///
+/// assert_eq!(span.start(), 69);
+/// assert_eq!(span.end(), 74);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     start: usize,
     end: usize,
 }
 
 impl Span {
+    /// Creates a new `Span` with specified start and end offset.
     pub fn new(start: usize, end: usize) -> Span {
         debug_assert!(start <= end);
 
         Span { start, end }
     }
 
+    /// Creates the smallest `Span` covering both specified spans.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sari::Span;
+    ///
+    /// let span = Span::cover(Span::new(0, 1), Span::new(4, 5));
+    ///
+    /// assert_eq!(span, Span::new(0, 5));
+    /// ```
     pub fn cover(a: Span, b: Span) -> Span {
         let start = a.start().min(b.start());
         let end = a.end().max(b.end());
@@ -21,10 +59,12 @@ impl Span {
         Span::new(start, end)
     }
 
+    /// Returns the start offset.
     pub fn start(&self) -> usize {
         self.start
     }
 
+    /// Returns the end offset.
     pub fn end(&self) -> usize {
         self.end
     }
@@ -37,7 +77,13 @@ pub trait Spanned {
 /// Position in source code.
 ///
 /// The position is represented by its character offset, line, and column. The
-/// offset is zero-based, the line and column are one-based.
+/// offset is zero-based, the line and column are one-based. The column counts
+/// characters too.
+///
+/// Because character offsets can't be used to slice a `&str` and editors often
+/// count columns in UTF-16 code units (e.g. in the Language Server Protocol),
+/// the position also provides its byte offset and UTF-16 column. For ASCII
+/// input, these are the same as the character offset and column.
 ///
 /// The `Eq`, `PartialEq`, `Ord`, and `PartialOrd` traits are implemented in
 /// terms of the character offset. This reflects an assumption that the line and
@@ -55,16 +101,24 @@ pub trait Spanned {
 /// assert_eq!(pos.column(), 7);
 /// ```
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourcePos {
     offset: usize,
     line: usize,
     column: usize,
+    byte_offset: usize,
+    utf16_column: usize,
 }
 
 impl SourcePos {
     /// Creates a new `SourcePos` with specified character offset, line, and
     /// column.
     ///
+    /// The byte offset and UTF-16 column are set to the character offset and
+    /// column, as if the source code was ASCII. Use
+    /// [`with_byte_offset`](SourcePos::with_byte_offset) and
+    /// [`with_utf16_column`](SourcePos::with_utf16_column) to set them.
+    ///
     /// # Example
     ///
     /// ```
@@ -81,9 +135,47 @@ impl SourcePos {
             offset,
             line,
             column,
+            byte_offset: offset,
+            utf16_column: column,
         }
     }
 
+    /// Returns the position with specified byte offset.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sari::SourcePos;
+    ///
+    /// // position of `1` in `"€ 1"`
+    /// let pos = SourcePos::new(2, 1, 3).with_byte_offset(4);
+    ///
+    /// assert_eq!(pos.offset(), 2);
+    /// assert_eq!(pos.byte_offset(), 4);
+    /// ```
+    pub fn with_byte_offset(mut self, byte_offset: usize) -> SourcePos {
+        self.byte_offset = byte_offset;
+        self
+    }
+
+    /// Returns the position with specified UTF-16 column.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use sari::SourcePos;
+    ///
+    /// // position of `1` in `"🦀 1"`
+    /// let pos = SourcePos::new(2, 1, 3).with_utf16_column(4);
+    ///
+    /// assert_eq!(pos.column(), 3);
+    /// assert_eq!(pos.utf16_column(), 4);
+    /// ```
+    pub fn with_utf16_column(mut self, utf16_column: usize) -> SourcePos {
+        self.utf16_column = utf16_column;
+        self
+    }
+
     /// Returns the character offset.
     pub fn offset(&self) -> usize {
         self.offset
@@ -98,6 +190,16 @@ impl SourcePos {
     pub fn column(&self) -> usize {
         self.column
     }
+
+    /// Returns the byte offset in the UTF-8 encoded source code.
+    pub fn byte_offset(&self) -> usize {
+        self.byte_offset
+    }
+
+    /// Returns the column in UTF-16 code units.
+    pub fn utf16_column(&self) -> usize {
+        self.utf16_column
+    }
 }
 
 impl Eq for SourcePos {}
@@ -148,6 +250,7 @@ impl fmt::Display for SourcePos {
 /// assert_eq!(span.end(), SourcePos::new(74, 5, 12));
 /// ```
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceSpan {
     start: SourcePos,
     end: SourcePos,
@@ -197,6 +300,29 @@ impl SourceSpan {
     pub fn end(&self) -> SourcePos {
         self.end
     }
+
+    /// Returns the text of the span in specified source code.
+    ///
+    /// The text is determined using the byte offsets of the positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the byte offsets are out of bounds of `source` or don't lie on
+    /// character boundaries.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let source = "/* € */ 1 / 0";
+    /// let error = sari::eval(source).unwrap_err();
+    ///
+    /// assert_eq!(error.span().start().offset(), 8);
+    /// assert_eq!(error.span().start().byte_offset(), 10);
+    /// assert_eq!(error.span().text(source), "1 / 0");
+    /// ```
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.byte_offset..self.end.byte_offset]
+    }
 }
 
 impl fmt::Display for SourceSpan {
@@ -205,25 +331,87 @@ impl fmt::Display for SourceSpan {
     }
 }
 
+#[derive(Debug)]
 pub struct SourceMap {
+    source: Option<(SourceId, String)>,
     line_starts: Vec<usize>,
+    wide_chars: Vec<WideChar>,
+}
+
+// Non-ASCII character, which takes more than one byte in UTF-8 and possibly
+// more than one code unit in UTF-16. The extra bytes and code units are
+// cumulative, i.e. they include all preceding wide characters.
+#[derive(Debug)]
+struct WideChar {
+    pos: usize,
+    extra_bytes: usize,
+    extra_utf16: usize,
 }
 
 impl SourceMap {
     pub fn new() -> SourceMap {
         SourceMap {
+            source: None,
             // There is always at least one line, starting at offset 0. This is
             // the case even for an empty input.
             line_starts: vec![0],
+            wide_chars: vec![],
         }
     }
 
+    // Creates a source map of a source in a `SourceDb`. Errors created by the
+    // map refer to the source.
+    pub fn with_source(id: SourceId, name: &str) -> SourceMap {
+        SourceMap {
+            source: Some((id, name.to_string())),
+            ..SourceMap::new()
+        }
+    }
+
+    // Creates a source map of the whole input at once, for input that was
+    // scanned without filling in a source map.
+    pub fn from_input(input: &str) -> SourceMap {
+        let mut source_map = SourceMap::new();
+
+        for (pos, ch) in input.chars().enumerate() {
+            source_map.add_char(pos, ch);
+
+            if ch == '\n' {
+                source_map.add_line_start(pos + 1);
+            }
+        }
+
+        source_map
+    }
+
+    // Resets the map to the state of a new one, keeping its memory.
+    pub fn clear(&mut self) {
+        self.source = None;
+        self.line_starts.truncate(1);
+        self.wide_chars.clear();
+    }
+
     pub fn add_line_start(&mut self, pos: usize) {
         debug_assert!(self.line_starts[self.line_starts.len() - 1] < pos);
 
         self.line_starts.push(pos);
     }
 
+    pub fn add_char(&mut self, pos: usize, ch: char) {
+        if ch.is_ascii() {
+            return;
+        }
+
+        let (extra_bytes, extra_utf16) = self.extras_before(pos);
+        debug_assert!(self.wide_chars.last().is_none_or(|last| last.pos < pos));
+
+        self.wide_chars.push(WideChar {
+            pos,
+            extra_bytes: extra_bytes + ch.len_utf8() - 1,
+            extra_utf16: extra_utf16 + ch.len_utf16() - 1,
+        });
+    }
+
     pub fn map_span(&self, span: Span) -> SourceSpan {
         let start = self.map_pos(span.start());
         let end = self.map_pos(span.end());
@@ -231,6 +419,21 @@ impl SourceMap {
         SourceSpan::new(start, end)
     }
 
+    pub fn error(&self, span: Span, message: impl Into<String>) -> Error {
+        self.attach_source(Error::new(self.map_span(span), message))
+    }
+
+    pub fn limit_error(&self, span: Span, limit: Limit) -> Error {
+        self.attach_source(Error::limit_exceeded(self.map_span(span), limit))
+    }
+
+    fn attach_source(&self, error: Error) -> Error {
+        match &self.source {
+            Some((id, name)) => error.with_source(*id, name),
+            None => error,
+        }
+    }
+
     fn map_pos(&self, pos: usize) -> SourcePos {
         // The algorithm below is binary search, modified in two ways:
         //
@@ -256,7 +459,30 @@ impl SourceMap {
             size = size - half - 1;
         }
 
-        SourcePos::new(pos, index + 1, pos - self.line_starts[index] + 1)
+        let line_start = self.line_starts[index];
+        let column = pos - line_start + 1;
+
+        let (extra_bytes, extra_utf16) = self.extras_before(pos);
+        let (_, line_extra_utf16) = self.extras_before(line_start);
+
+        SourcePos::new(pos, index + 1, column)
+            .with_byte_offset(pos + extra_bytes)
+            .with_utf16_column(column + extra_utf16 - line_extra_utf16)
+    }
+
+    // Returns the extra bytes and UTF-16 code units taken by wide characters
+    // before specified character offset.
+    fn extras_before(&self, pos: usize) -> (usize, usize) {
+        let index = self.wide_chars.partition_point(|ch| ch.pos < pos);
+
+        match index.checked_sub(1) {
+            Some(index) => {
+                let ch = &self.wide_chars[index];
+
+                (ch.extra_bytes, ch.extra_utf16)
+            }
+            None => (0, 0),
+        }
     }
 }
 
@@ -318,6 +544,16 @@ mod tests {
         assert_eq!(span.to_string(), "1:5-2:3");
     }
 
+    #[test]
+    fn source_span_text_works() {
+        let span = SourceSpan::new(
+            SourcePos::new(2, 1, 3).with_byte_offset(4),
+            SourcePos::new(3, 1, 4).with_byte_offset(5),
+        );
+
+        assert_eq!(span.text("€ 1"), "1");
+    }
+
     #[test]
     fn source_map_mapping() {
         let mut source_map = SourceMap::new();
@@ -342,4 +578,34 @@ mod tests {
             SourceSpan::new(SourcePos::new(8, 3, 1), SourcePos::new(10, 3, 3))
         );
     }
+
+    #[test]
+    fn source_map_mapping_wide_chars() {
+        // "é\n€🦀x", scanned char by char
+        let mut source_map = SourceMap::new();
+        source_map.add_char(0, 'é');
+        source_map.add_char(1, '\n');
+        source_map.add_line_start(2);
+        source_map.add_char(2, '€');
+        source_map.add_char(3, '🦀');
+        source_map.add_char(4, 'x');
+
+        let map = |offset| {
+            let pos = source_map.map_pos(offset);
+            (
+                pos.offset(),
+                pos.line(),
+                pos.column(),
+                pos.byte_offset(),
+                pos.utf16_column(),
+            )
+        };
+
+        assert_eq!(map(0), (0, 1, 1, 0, 1));
+        assert_eq!(map(1), (1, 1, 2, 2, 2));
+        assert_eq!(map(2), (2, 2, 1, 3, 1));
+        assert_eq!(map(3), (3, 2, 2, 6, 2));
+        assert_eq!(map(4), (4, 2, 3, 10, 4));
+        assert_eq!(map(5), (5, 2, 4, 11, 5));
+    }
 }
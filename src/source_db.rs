@@ -0,0 +1,197 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::expression::Expression;
+use crate::limits::Limits;
+
+/// Identifier of a source in a [`SourceDb`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SourceId(u32);
+
+impl SourceId {
+    fn new(index: usize) -> SourceId {
+        SourceId(u32::try_from(index).expect("too many sources"))
+    }
+
+    fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Database of named sources.
+///
+/// The database holds multiple sources (e.g. expressions loaded from several
+/// files), each identified by a [`SourceId`]. Errors of expressions parsed from
+/// the database refer to their source, so they can be reported with the source
+/// name.
+///
+/// # Examples
+///
+/// ```
+/// use sari::SourceDb;
+///
+/// let mut db = SourceDb::new();
+/// let base = db.add("base.sari", "1 + 2");
+/// let pricing = db.add("pricing.sari", "// discount\n(1 + 2) / 0");
+///
+/// assert_eq!(db.eval(base), Ok(3));
+///
+/// let error = db.eval(pricing).unwrap_err();
+/// assert_eq!(error.source_id(), Some(pricing));
+/// assert_eq!(error.to_string(), "pricing.sari:2:1-2:12: division by zero");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SourceDb {
+    sources: Vec<Source>,
+}
+
+#[derive(Clone, Debug)]
+struct Source {
+    name: String,
+    text: String,
+}
+
+impl SourceDb {
+    /// Creates a new empty `SourceDb`.
+    pub fn new() -> SourceDb {
+        SourceDb { sources: vec![] }
+    }
+
+    /// Adds a source with specified name and text and returns its identifier.
+    ///
+    /// The name is typically a file name. It doesn't have to be unique.
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> SourceId {
+        let id = SourceId::new(self.sources.len());
+        self.sources.push(Source {
+            name: name.into(),
+            text: text.into(),
+        });
+
+        id
+    }
+
+    /// Returns the name of a source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` doesn't identify a source in the database.
+    pub fn name(&self, id: SourceId) -> &str {
+        &self.source(id).name
+    }
+
+    /// Returns the text of a source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` doesn't identify a source in the database.
+    pub fn text(&self, id: SourceId) -> &str {
+        &self.source(id).text
+    }
+
+    /// Evaluates a source and returns the result.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the evaluation fails. The error refers to the
+    /// source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` doesn't identify a source in the database.
+    pub fn eval(&self, id: SourceId) -> Result<i32, Error> {
+        self.parse(id)?.eval()
+    }
+
+    /// Parses a source and returns the expression.
+    ///
+    /// Errors of the parsing and of evaluating the returned expression refer
+    /// to the source.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` doesn't identify a source in the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::SourceDb;
+    ///
+    /// let mut db = SourceDb::new();
+    /// let id = db.add("pricing.sari", "(1 + 2");
+    ///
+    /// let error = db.parse(id).unwrap_err();
+    /// assert_eq!(error.to_string(), "pricing.sari:1:7-1:7: expected `)`");
+    /// ```
+    pub fn parse(&self, id: SourceId) -> Result<Expression, Error> {
+        Engine::new().parse_source(self, id)
+    }
+
+    /// Parses a source with resource limits and returns the expression.
+    ///
+    /// See [`parse`](SourceDb::parse) and [`Limits`] for details. To parse
+    /// sources with other settings, use [`Engine::parse_source`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error`] if the parsing fails or a limit is exceeded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` doesn't identify a source in the database.
+    pub fn parse_with_limits(&self, id: SourceId, limits: Limits) -> Result<Expression, Error> {
+        Engine::new().with_limits(limits).parse_source(self, id)
+    }
+
+    fn source(&self, id: SourceId) -> &Source {
+        self.sources.get(id.index()).expect("invalid source ID")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn holds_sources() {
+        let mut db = SourceDb::new();
+        let a = db.add("a.sari", "1");
+        let b = db.add("b.sari", "2");
+
+        assert_ne!(a, b);
+        assert_eq!((db.name(a), db.text(a)), ("a.sari", "1"));
+        assert_eq!((db.name(b), db.text(b)), ("b.sari", "2"));
+    }
+
+    #[test]
+    fn attaches_sources_to_errors() {
+        let mut db = SourceDb::new();
+        let a = db.add("a.sari", "1 +");
+        let b = db.add("b.sari", "1 / 0");
+
+        let error = db.parse(a).unwrap_err();
+        assert_eq!(error.source_id(), Some(a));
+        assert_eq!(error.source_name(), Some("a.sari"));
+
+        let expr = db.parse(b).unwrap();
+        for error in [
+            expr.eval().unwrap_err(),
+            expr.optimize().eval().unwrap_err(),
+            expr.to_bytecode().eval().unwrap_err(),
+            expr.compile().eval().unwrap_err(),
+        ] {
+            assert_eq!(error.source_id(), Some(b));
+            assert_eq!(error.to_string(), "b.sari:1:1-1:6: division by zero");
+        }
+    }
+}
@@ -1,21 +1,56 @@
 use crate::source::{Span, Spanned};
 
+/// Kind of a token.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum TokenKind {
+    /// `+`
     Plus,
+    /// `-`
     Minus,
+    /// `*`
     Star,
+    /// `/`
     Slash,
+    /// `(`
     LParen,
+    /// `)`
     RParen,
 
+    /// Integer literal.
     Int,
 
+    /// Whitespace other than a newline.
+    ///
+    /// Whitespace is only produced as trivia in the
+    /// [concrete syntax tree](crate::cst).
+    Whitespace,
+    /// Newline.
+    ///
+    /// Newlines are only produced as trivia in the
+    /// [concrete syntax tree](crate::cst).
+    Newline,
+    /// Line comment (`// ...`).
+    LineComment,
+    /// Block comment (`/* ... */`).
+    BlockComment,
+
+    /// Character that doesn't start any valid token.
     Error,
+    /// End of input.
     Eof,
 }
 
 impl TokenKind {
+    /// Returns a description of the kind, as used in error messages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::TokenKind;
+    ///
+    /// assert_eq!(TokenKind::Plus.as_str(), "`+`");
+    /// assert_eq!(TokenKind::Int.as_str(), "integer literal");
+    /// ```
     pub fn as_str(&self) -> &'static str {
         match self {
             TokenKind::Plus => "`+`",
@@ -27,10 +62,36 @@ impl TokenKind {
 
             TokenKind::Int => "integer literal",
 
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Newline => "newline",
+            TokenKind::LineComment => "line comment",
+            TokenKind::BlockComment => "block comment",
+
             TokenKind::Error => "error",
             TokenKind::Eof => "end of input",
         }
     }
+
+    /// Returns `true` if the kind is trivia, i.e. it doesn't affect the meaning
+    /// of an expression.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sari::TokenKind;
+    ///
+    /// assert!(TokenKind::Whitespace.is_trivia());
+    /// assert!(!TokenKind::Plus.is_trivia());
+    /// ```
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace
+                | TokenKind::Newline
+                | TokenKind::LineComment
+                | TokenKind::BlockComment
+        )
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -75,6 +136,22 @@ impl Token {
         Token::new(span, TokenKind::Int, TokenValue::Int(value))
     }
 
+    pub fn whitespace(span: Span) -> Token {
+        Token::simple(span, TokenKind::Whitespace)
+    }
+
+    pub fn newline(span: Span) -> Token {
+        Token::simple(span, TokenKind::Newline)
+    }
+
+    pub fn line_comment(span: Span) -> Token {
+        Token::simple(span, TokenKind::LineComment)
+    }
+
+    pub fn block_comment(span: Span) -> Token {
+        Token::simple(span, TokenKind::BlockComment)
+    }
+
     pub fn error(span: Span) -> Token {
         Token::simple(span, TokenKind::Error)
     }
@@ -95,6 +172,10 @@ impl Token {
         self.kind
     }
 
+    pub fn value(&self) -> TokenValue {
+        self.value
+    }
+
     pub fn int_value(&self) -> i32 {
         let TokenValue::Int(value) = self.value else {
             panic!("token {self:?} doesn't have an integer value")
@@ -0,0 +1,167 @@
+use crate::limits::Limits;
+use crate::scanner::Scanner;
+use crate::source::{SourceMap, SourceSpan, Spanned};
+use crate::token::{TokenKind, TokenValue};
+
+/// Token in source code.
+///
+/// Tokens are produced by [`sari::tokenize`](crate::tokenize).
+///
+/// # Examples
+///
+/// ```
+/// use sari::{SourcePos, SourceSpan, TokenKind};
+///
+/// let token = sari::tokenize("42").next().unwrap();
+///
+/// assert_eq!(token.kind(), TokenKind::Int);
+/// assert_eq!(
+///     token.span(),
+///     SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(2, 1, 3)),
+/// );
+/// assert_eq!(token.int_value(), Some(42));
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SourceToken {
+    kind: TokenKind,
+    span: SourceSpan,
+    int_value: Option<i32>,
+}
+
+impl SourceToken {
+    /// Creates a new `SourceToken` with specified kind, span, and integer
+    /// value.
+    pub fn new(kind: TokenKind, span: SourceSpan, int_value: Option<i32>) -> SourceToken {
+        SourceToken {
+            kind,
+            span,
+            int_value,
+        }
+    }
+
+    /// Returns the kind.
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    /// Returns the span.
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+
+    /// Returns the value of an integer literal.
+    ///
+    /// Returns `None` if the token is not an integer literal.
+    pub fn int_value(&self) -> Option<i32> {
+        self.int_value
+    }
+}
+
+/// Iterator over tokens in source code.
+///
+/// The iterator is created by [`sari::tokenize`](crate::tokenize). See its
+/// documentation for details.
+pub struct Tokens<'a> {
+    scanner: Scanner<'a>,
+    done: bool,
+}
+
+impl Tokens<'_> {
+    pub(crate) fn new(input: &str) -> Tokens<'_> {
+        Tokens {
+            scanner: Scanner::new(input, SourceMap::new(), Limits::default()).keep_trivia(),
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = SourceToken;
+
+    fn next(&mut self) -> Option<SourceToken> {
+        if self.done {
+            return None;
+        }
+
+        // Comments are useful e.g. for syntax highlighting, so only whitespace
+        // and newlines are skipped.
+        let token = loop {
+            let token = self.scanner.scan_token();
+            if !matches!(token.kind(), TokenKind::Whitespace | TokenKind::Newline) {
+                break token;
+            }
+        };
+        if token.kind() == TokenKind::Eof {
+            self.done = true;
+            return None;
+        }
+
+        // The scanner registers line starts as it goes, so the token span can
+        // be mapped as soon as the token is scanned.
+        let span = self.scanner.map_span(token.span());
+        let int_value = match token.value() {
+            TokenValue::None => None,
+            TokenValue::Int(value) => Some(value),
+        };
+
+        Some(SourceToken::new(token.kind(), span, int_value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::source::SourcePos;
+
+    fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> SourceSpan {
+        SourceSpan::new(
+            SourcePos::new(start.0, start.1, start.2),
+            SourcePos::new(end.0, end.1, end.2),
+        )
+    }
+
+    #[test]
+    fn tokenizes_empty_input() {
+        assert_eq!(Tokens::new("").collect::<Vec<_>>(), vec![]);
+        assert_eq!(Tokens::new("  \n ").collect::<Vec<_>>(), vec![]);
+    }
+
+    #[test]
+    fn tokenizes_input() {
+        assert_eq!(
+            Tokens::new("(1 +\n 23) ?").collect::<Vec<_>>(),
+            vec![
+                SourceToken::new(TokenKind::LParen, span((0, 1, 1), (1, 1, 2)), None),
+                SourceToken::new(TokenKind::Int, span((1, 1, 2), (2, 1, 3)), Some(1)),
+                SourceToken::new(TokenKind::Plus, span((3, 1, 4), (4, 1, 5)), None),
+                SourceToken::new(TokenKind::Int, span((6, 2, 2), (8, 2, 4)), Some(23)),
+                SourceToken::new(TokenKind::RParen, span((8, 2, 4), (9, 2, 5)), None),
+                SourceToken::new(TokenKind::Error, span((10, 2, 6), (11, 2, 7)), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn tokenizes_comments() {
+        assert_eq!(
+            Tokens::new("1 // a\n/* b").collect::<Vec<_>>(),
+            vec![
+                SourceToken::new(TokenKind::Int, span((0, 1, 1), (1, 1, 2)), Some(1)),
+                SourceToken::new(TokenKind::LineComment, span((2, 1, 3), (6, 1, 7)), None),
+                SourceToken::new(TokenKind::BlockComment, span((7, 2, 1), (11, 2, 5)), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn stops_after_end_of_input() {
+        let mut tokens = Tokens::new("1");
+
+        assert!(tokens.next().is_some());
+        assert_eq!(tokens.next(), None);
+        assert_eq!(tokens.next(), None);
+    }
+}
@@ -0,0 +1,229 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::ast::{Ast, BinaryOp, Expr, ExprId, Step};
+use crate::error::Error;
+use crate::source::SourceSpan;
+
+/// Single reduction performed during evaluation.
+///
+/// A reduction is recorded for each binary expression whose operands were
+/// evaluated and combined into a value.
+///
+/// # Examples
+///
+/// ```
+/// use sari::ast::BinaryOp;
+///
+/// let trace = sari::parse("(1 + 2) * 3")?.eval_traced();
+/// let reduction = &trace.reductions()[0];
+///
+/// assert_eq!(reduction.left(), 1);
+/// assert_eq!(reduction.op(), BinaryOp::Add);
+/// assert_eq!(reduction.right(), 2);
+/// assert_eq!(reduction.value(), 3);
+/// assert_eq!(reduction.span().start().offset(), 1);
+/// assert_eq!(reduction.span().end().offset(), 6);
+/// # Ok::<(), sari::Error>(())
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Reduction {
+    expr: ExprId,
+    left: i32,
+    op: BinaryOp,
+    right: i32,
+    value: i32,
+    span: SourceSpan,
+}
+
+impl Reduction {
+    pub(crate) fn new(
+        expr: ExprId,
+        left: i32,
+        op: BinaryOp,
+        right: i32,
+        value: i32,
+        span: SourceSpan,
+    ) -> Reduction {
+        Reduction {
+            expr,
+            left,
+            op,
+            right,
+            value,
+            span,
+        }
+    }
+
+    /// Returns the identifier of the reduced binary expression in the
+    /// [AST](crate::Expression::ast).
+    pub fn expr(&self) -> ExprId {
+        self.expr
+    }
+
+    /// Returns the value of the left operand.
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+
+    /// Returns the operator.
+    pub fn op(&self) -> BinaryOp {
+        self.op
+    }
+
+    /// Returns the value of the right operand.
+    pub fn right(&self) -> i32 {
+        self.right
+    }
+
+    /// Returns the value the expression was reduced to.
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Returns the span of the reduced expression.
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+/// Record of an evaluation, step by step.
+///
+/// A trace is created by [`Expression::eval_traced`](crate::Expression::eval_traced).
+/// It holds the result of the evaluation and the [`Reduction`]s performed, in
+/// the order they were performed. If the evaluation fails, the trace holds
+/// the reductions performed before the failure.
+///
+/// The [`Display`](fmt::Display) implementation prints the expression after
+/// each reduction, separated by arrows.
+///
+/// # Examples
+///
+/// ```
+/// let trace = sari::parse("(1 + 2) * 3")?.eval_traced();
+///
+/// assert_eq!(trace.result(), Ok(9));
+/// assert_eq!(trace.reductions().len(), 2);
+/// assert_eq!(trace.to_string(), "(1 + 2) * 3 → 3 * 3 → 9");
+/// # Ok::<(), sari::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Trace {
+    ast: Ast,
+    reductions: Vec<Reduction>,
+    result: Result<i32, Error>,
+}
+
+impl Trace {
+    pub(crate) fn new(ast: Ast, reductions: Vec<Reduction>, result: Result<i32, Error>) -> Trace {
+        Trace {
+            ast,
+            reductions,
+            result,
+        }
+    }
+
+    /// Returns the result of the evaluation.
+    pub fn result(&self) -> Result<i32, Error> {
+        self.result.clone()
+    }
+
+    /// Returns the reductions performed during the evaluation.
+    pub fn reductions(&self) -> &[Reduction] {
+        &self.reductions
+    }
+
+    // Copies an expression to `out`, replacing the reduced expressions with
+    // their values.
+    fn substitute(&self, id: ExprId, values: &[Option<i32>], out: &mut Ast) -> ExprId {
+        // Copied expressions whose parents aren't copied yet.
+        let mut copied = vec![];
+
+        let mut walk = self.ast.walk(id);
+        while let Some(step) = walk.next() {
+            match step {
+                Step::Enter(id) => {
+                    if values[id.index()].is_some() {
+                        walk.skip_children();
+                    }
+                }
+                Step::Leave(id) => {
+                    let expr = &self.ast[id];
+
+                    let id = match (values[id.index()], expr) {
+                        (Some(value), _) => out.int(expr.span(), value),
+                        (None, Expr::Int(expr)) => out.int(expr.span, expr.value),
+                        (None, Expr::Group(expr)) => {
+                            let inner = pop_copied(&mut copied);
+
+                            out.group(expr.span, inner)
+                        }
+                        (None, Expr::Binary(expr)) => {
+                            let right = pop_copied(&mut copied);
+                            let left = pop_copied(&mut copied);
+
+                            out.binary(expr.span, expr.op, left, right)
+                        }
+                    };
+
+                    copied.push(id);
+                }
+            }
+        }
+
+        pop_copied(&mut copied)
+    }
+}
+
+fn pop_copied(copied: &mut Vec<ExprId>) -> ExprId {
+    copied.pop().expect("child expressions are copied")
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ast)?;
+
+        let mut values = vec![None; self.ast.len()];
+        for reduction in &self.reductions {
+            values[reduction.expr.index()] = Some(reduction.value);
+
+            let mut ast = Ast::new();
+            let root = self.substitute(self.ast.root(), &values, &mut ast);
+            write!(f, " → {}", ast.display(root))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    fn explain(expr: &str) -> String {
+        crate::eval_traced(expr).unwrap().to_string()
+    }
+
+    #[test]
+    fn displays_steps() {
+        assert_eq!(explain("1"), "1");
+        assert_eq!(explain("(1)"), "1");
+        assert_eq!(explain("1 + 2"), "1 + 2 → 3");
+        assert_eq!(explain("(1 + 2) * 3"), "(1 + 2) * 3 → 3 * 3 → 9");
+        assert_eq!(
+            explain("1 * 2 + 3 * 4"),
+            "1 * 2 + 3 * 4 → 2 + 3 * 4 → 2 + 12 → 14",
+        );
+        assert_eq!(explain("1 - (2 - 3)"), "1 - (2 - 3) → 1 - (0 - 1) → 2");
+    }
+
+    #[test]
+    fn displays_steps_before_errors() {
+        assert_eq!(explain("1 / 0"), "1 / 0");
+        assert_eq!(
+            explain("(1 + 2) / (3 - 3)"),
+            "(1 + 2) / (3 - 3) → 3 / (3 - 3) → 3 / 0"
+        );
+    }
+}
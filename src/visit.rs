@@ -0,0 +1,443 @@
+//! Traversal of the abstract syntax tree.
+//!
+//! There are two ways to traverse an [`Ast`]:
+//!
+//!   * A [`Visitor`] is called for each node of the tree. All its methods have
+//!     default implementations that do nothing, so an implementation only
+//!     overrides the methods for the nodes it is interested in. The traversal
+//!     itself is done by [`walk_expr`].
+//!
+//!   * A [`Fold`] computes a value for each node from the values of its
+//!     children. It has a required method for each node kind, so adding a new
+//!     kind is a compile error in every implementation. A fold can also build a
+//!     new tree, making it useful for transformations.
+//!
+//! Both traversals use an explicit stack instead of recursion, so they handle
+//! arbitrarily deep trees, such as long chains of operators.
+//!
+//! A traversal starts at a given expression, usually the [root](Ast::root).
+//! An [empty](Ast::is_empty) `Ast` has no root, so check it first when the
+//! tree is built by hand.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::ast::{Ast, BinaryExpr, BinaryOp, Expr, ExprId, GroupExpr, IntExpr, Step};
+use crate::source::Span;
+
+/// Read-only visitor of an abstract syntax tree.
+///
+/// See the [module documentation](self) for details.
+///
+/// # Examples
+///
+/// ```
+/// use sari::ast::{Ast, IntExpr};
+/// use sari::visit::{self, Visitor};
+///
+/// struct IntCounter(usize);
+///
+/// impl Visitor for IntCounter {
+///     fn visit_int_expr(&mut self, _ast: &Ast, _expr: &IntExpr) {
+///         self.0 += 1;
+///     }
+/// }
+///
+/// let expr = sari::parse("(1 + 2) * 3")?;
+/// let mut counter = IntCounter(0);
+/// counter.visit_expr(expr.ast(), expr.ast().root());
+///
+/// assert_eq!(counter.0, 3);
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub trait Visitor {
+    /// Visits an expression and its children.
+    ///
+    /// This is the entry point of a traversal, so it is called only for the
+    /// expression the traversal starts at. The children are visited by
+    /// [`walk_expr`] without calling it again. To skip or wrap the children,
+    /// override [`enter_expr`](Visitor::enter_expr) and
+    /// [`leave_expr`](Visitor::leave_expr), which are called for every
+    /// expression.
+    fn visit_expr(&mut self, ast: &Ast, id: ExprId) {
+        walk_expr(self, ast, id);
+    }
+
+    /// Called before an expression and its children are visited. If it returns
+    /// `false`, they are skipped.
+    fn enter_expr(&mut self, _ast: &Ast, _id: ExprId) -> bool {
+        true
+    }
+
+    /// Called after an expression and its children are visited, unless they
+    /// were skipped.
+    fn leave_expr(&mut self, _ast: &Ast, _id: ExprId) {}
+
+    /// Visits an integer literal.
+    fn visit_int_expr(&mut self, _ast: &Ast, _expr: &IntExpr) {}
+
+    /// Visits a parenthesized expression, before the expression inside the
+    /// parentheses.
+    fn visit_group_expr(&mut self, _ast: &Ast, _expr: &GroupExpr) {}
+
+    /// Visits a binary expression, before its operands.
+    fn visit_binary_expr(&mut self, _ast: &Ast, _expr: &BinaryExpr) {}
+
+    /// Visits a binary operator, between the operands.
+    fn visit_binary_op(&mut self, _op: BinaryOp) {}
+
+    /// Visits a span of an expression, right after the expression.
+    fn visit_span(&mut self, _span: Span) {}
+}
+
+/// Walks an expression and its children depth-first, left to right.
+///
+/// For each expression, [`Visitor::enter_expr`] is called first. Unless it
+/// skips the expression, the visitor method for its kind is called, followed by
+/// [`Visitor::visit_span`]. Then the children are walked, with
+/// [`Visitor::visit_binary_op`] called between the operands of a binary
+/// expression, and finally [`Visitor::leave_expr`] is called.
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, ast: &Ast, id: ExprId) {
+    enum Item {
+        Expr(ExprId),
+        Op(BinaryOp),
+        Leave(ExprId),
+    }
+
+    let mut stack = vec![Item::Expr(id)];
+
+    while let Some(item) = stack.pop() {
+        let id = match item {
+            Item::Expr(id) => id,
+            Item::Op(op) => {
+                visitor.visit_binary_op(op);
+                continue;
+            }
+            Item::Leave(id) => {
+                visitor.leave_expr(ast, id);
+                continue;
+            }
+        };
+
+        if !visitor.enter_expr(ast, id) {
+            continue;
+        }
+        stack.push(Item::Leave(id));
+
+        match &ast[id] {
+            Expr::Int(expr) => {
+                visitor.visit_int_expr(ast, expr);
+                visitor.visit_span(expr.span);
+            }
+            Expr::Group(expr) => {
+                visitor.visit_group_expr(ast, expr);
+                visitor.visit_span(expr.span);
+
+                stack.push(Item::Expr(expr.expr));
+            }
+            Expr::Binary(expr) => {
+                visitor.visit_binary_expr(ast, expr);
+                visitor.visit_span(expr.span);
+
+                stack.push(Item::Expr(expr.right));
+                stack.push(Item::Op(expr.op));
+                stack.push(Item::Expr(expr.left));
+            }
+        }
+    }
+}
+
+/// Fold of an abstract syntax tree, computing a value for each node from the
+/// values of its children.
+///
+/// See the [module documentation](self) for details.
+///
+/// # Examples
+///
+/// Folding into a value:
+///
+/// ```
+/// use sari::ast::{BinaryExpr, GroupExpr, IntExpr};
+/// use sari::visit::Fold;
+///
+/// struct Depth;
+///
+/// impl Fold for Depth {
+///     type Output = usize;
+///
+///     fn fold_int_expr(&mut self, _expr: &IntExpr) -> usize {
+///         1
+///     }
+///
+///     fn fold_group_expr(&mut self, _expr: &GroupExpr, inner: usize) -> usize {
+///         inner + 1
+///     }
+///
+///     fn fold_binary_expr(&mut self, _expr: &BinaryExpr, left: usize, right: usize) -> usize {
+///         left.max(right) + 1
+///     }
+/// }
+///
+/// let expr = sari::parse("(1 + 2) * 3")?;
+///
+/// assert_eq!(Depth.fold(expr.ast(), expr.ast().root()), 4);
+/// # Ok::<(), sari::Error>(())
+/// ```
+///
+/// Folding into a new tree:
+///
+/// ```
+/// use sari::ast::{Ast, BinaryExpr, BinaryOp, ExprId, GroupExpr, IntExpr};
+/// use sari::visit::Fold;
+///
+/// // Swaps addition and multiplication.
+/// struct Swap(Ast);
+///
+/// impl Fold for Swap {
+///     type Output = ExprId;
+///
+///     fn fold_int_expr(&mut self, expr: &IntExpr) -> ExprId {
+///         self.0.int(expr.span, expr.value)
+///     }
+///
+///     fn fold_group_expr(&mut self, expr: &GroupExpr, inner: ExprId) -> ExprId {
+///         self.0.group(expr.span, inner)
+///     }
+///
+///     fn fold_binary_expr(&mut self, expr: &BinaryExpr, left: ExprId, right: ExprId) -> ExprId {
+///         let op = match expr.op {
+///             BinaryOp::Add => BinaryOp::Mul,
+///             BinaryOp::Mul => BinaryOp::Add,
+///             op => op,
+///         };
+///
+///         self.0.binary(expr.span, op, left, right)
+///     }
+/// }
+///
+/// let expr = sari::parse("(1 + 2) * 3")?;
+/// let mut swap = Swap(Ast::new());
+/// swap.fold(expr.ast(), expr.ast().root());
+///
+/// assert_eq!(swap.0.to_string(), "1 * 2 + 3");
+/// # Ok::<(), sari::Error>(())
+/// ```
+pub trait Fold {
+    /// Value computed for each node.
+    type Output;
+
+    /// Folds an expression and its children.
+    fn fold(&mut self, ast: &Ast, id: ExprId) -> Self::Output {
+        // Values of the expressions which are folded, but whose parents aren't.
+        let mut values = vec![];
+
+        for step in ast.walk(id) {
+            let Step::Leave(id) = step else {
+                continue;
+            };
+
+            let value = match &ast[id] {
+                Expr::Int(expr) => self.fold_int_expr(expr),
+                Expr::Group(expr) => {
+                    let inner = pop_value(&mut values);
+
+                    self.fold_group_expr(expr, inner)
+                }
+                Expr::Binary(expr) => {
+                    let right = pop_value(&mut values);
+                    let left = pop_value(&mut values);
+
+                    self.fold_binary_expr(expr, left, right)
+                }
+            };
+
+            values.push(value);
+        }
+
+        pop_value(&mut values)
+    }
+
+    /// Folds an integer literal.
+    fn fold_int_expr(&mut self, expr: &IntExpr) -> Self::Output;
+
+    /// Folds a parenthesized expression, given the value of the expression
+    /// inside the parentheses.
+    fn fold_group_expr(&mut self, expr: &GroupExpr, inner: Self::Output) -> Self::Output;
+
+    /// Folds a binary expression, given the values of its operands.
+    fn fold_binary_expr(
+        &mut self,
+        expr: &BinaryExpr,
+        left: Self::Output,
+        right: Self::Output,
+    ) -> Self::Output;
+}
+
+fn pop_value<T>(values: &mut Vec<T>) -> T {
+    values.pop().expect("child expressions are folded")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::string::String;
+
+    use super::*;
+    use crate::ast::build::{ast, binary, group, int};
+
+    fn example_ast() -> Ast {
+        // (1 + 2) * 3
+        ast(binary(
+            Span::new(0, 11),
+            BinaryOp::Mul,
+            group(
+                Span::new(0, 7),
+                binary(
+                    Span::new(1, 6),
+                    BinaryOp::Add,
+                    int(Span::new(1, 2), 1),
+                    int(Span::new(5, 6), 2),
+                ),
+            ),
+            int(Span::new(10, 11), 3),
+        ))
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+    }
+
+    impl Visitor for Recorder {
+        fn visit_int_expr(&mut self, _ast: &Ast, expr: &IntExpr) {
+            self.events.push(format!("int {}", expr.value));
+        }
+
+        fn visit_binary_op(&mut self, op: BinaryOp) {
+            self.events.push(format!("op {op:?}"));
+        }
+
+        fn visit_span(&mut self, span: Span) {
+            self.events
+                .push(format!("span {}..{}", span.start(), span.end()));
+        }
+    }
+
+    #[test]
+    fn visitor_visits_all_nodes_in_order() {
+        let ast = example_ast();
+        let mut recorder = Recorder::default();
+
+        recorder.visit_expr(&ast, ast.root());
+
+        assert_eq!(
+            recorder.events,
+            vec![
+                "span 0..11",
+                "span 0..7",
+                "span 1..6",
+                "int 1",
+                "span 1..2",
+                "op Add",
+                "int 2",
+                "span 5..6",
+                "op Mul",
+                "int 3",
+                "span 10..11",
+            ],
+        );
+    }
+
+    // Records the structure of the tree, skipping parenthesized expressions.
+    #[derive(Default)]
+    struct Pruner {
+        events: Vec<String>,
+        visits: usize,
+    }
+
+    impl Visitor for Pruner {
+        fn visit_expr(&mut self, ast: &Ast, id: ExprId) {
+            self.visits += 1;
+            walk_expr(self, ast, id);
+        }
+
+        fn enter_expr(&mut self, ast: &Ast, id: ExprId) -> bool {
+            let span = ast[id].span();
+            self.events
+                .push(format!("enter {}..{}", span.start(), span.end()));
+
+            !matches!(ast[id], Expr::Group(_))
+        }
+
+        fn leave_expr(&mut self, ast: &Ast, id: ExprId) {
+            let span = ast[id].span();
+            self.events
+                .push(format!("leave {}..{}", span.start(), span.end()));
+        }
+
+        fn visit_binary_op(&mut self, op: BinaryOp) {
+            self.events.push(format!("op {op:?}"));
+        }
+    }
+
+    #[test]
+    fn visitor_skips_and_wraps_children() {
+        let ast = example_ast();
+        let mut pruner = Pruner::default();
+
+        pruner.visit_expr(&ast, ast.root());
+
+        assert_eq!(
+            pruner.events,
+            vec![
+                "enter 0..11",
+                "enter 0..7",
+                "op Mul",
+                "enter 10..11",
+                "leave 10..11",
+                "leave 0..11",
+            ],
+        );
+        // `visit_expr` is called only for the root
+        assert_eq!(pruner.visits, 1);
+    }
+
+    struct Sum;
+
+    impl Fold for Sum {
+        type Output = i32;
+
+        fn fold_int_expr(&mut self, expr: &IntExpr) -> i32 {
+            expr.value
+        }
+
+        fn fold_group_expr(&mut self, _expr: &GroupExpr, inner: i32) -> i32 {
+            inner
+        }
+
+        fn fold_binary_expr(&mut self, _expr: &BinaryExpr, left: i32, right: i32) -> i32 {
+            left + right
+        }
+    }
+
+    #[test]
+    fn fold_folds_all_nodes() {
+        let ast = example_ast();
+
+        assert_eq!(Sum.fold(&ast, ast.root()), 6);
+    }
+
+    #[test]
+    fn traverses_long_chains_of_operators() {
+        let expr = crate::parse(&vec!["1"; 10000].join(" + ")).unwrap();
+        let ast = expr.ast();
+        let mut recorder = Recorder::default();
+
+        recorder.visit_expr(ast, ast.root());
+
+        // A span and a literal for each integer, a span and an operator for
+        // each binary expression.
+        assert_eq!(recorder.events.len(), 2 * 10000 + 2 * 9999);
+        assert_eq!(Sum.fold(ast, ast.root()), 10000);
+    }
+}
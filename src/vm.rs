@@ -0,0 +1,198 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::error::Error;
+use crate::program::{Instruction, Program};
+use crate::source::{SourceMap, Span};
+
+pub struct Vm<'a> {
+    program: &'a Program,
+    source_map: &'a SourceMap,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(program: &'a Program, source_map: &'a SourceMap) -> Vm<'a> {
+        Vm {
+            program,
+            source_map,
+        }
+    }
+
+    pub fn run(&self) -> Result<i32, Error> {
+        let mut stack = Vec::with_capacity(self.program.stack_size);
+
+        for (index, &instruction) in self.program.instructions.iter().enumerate() {
+            let value = match instruction {
+                Instruction::Int(value) => value,
+                Instruction::Binary(op) => {
+                    let (left, right) = pop_operands(&mut stack);
+
+                    op.apply(left, right)
+                        .ok_or_else(|| self.error(self.program.spans[index], "division by zero"))?
+                }
+            };
+
+            stack.push(value);
+        }
+
+        Ok(stack.pop().expect("empty stack"))
+    }
+
+    fn error(&self, span: Span, message: impl Into<String>) -> Error {
+        self.source_map.error(span, message)
+    }
+}
+
+fn pop_operands(stack: &mut Vec<i32>) -> (i32, i32) {
+    let right = stack.pop().expect("empty stack");
+    let left = stack.pop().expect("empty stack");
+
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::ast::BinaryOp;
+    use crate::source::{SourcePos, SourceSpan};
+
+    macro_rules! assert_runs {
+        ($instructions:expr, $value:expr $(,)?) => {
+            let source_map = SourceMap::new();
+
+            let instructions: Vec<Instruction> = $instructions;
+            let program = Program {
+                spans: vec![Span::new(0, 5); instructions.len()],
+                stack_size: instructions.len(),
+                instructions,
+            };
+            let vm = Vm::new(&program, &source_map);
+
+            assert_eq!(vm.run(), Ok($value));
+        };
+    }
+
+    macro_rules! assert_does_not_run {
+        ($instructions:expr, $error:expr $(,)?) => {
+            let source_map = SourceMap::new();
+
+            let instructions: Vec<Instruction> = $instructions;
+            let program = Program {
+                spans: vec![Span::new(0, 5); instructions.len()],
+                stack_size: instructions.len(),
+                instructions,
+            };
+            let vm = Vm::new(&program, &source_map);
+
+            assert_eq!(vm.run(), Err($error));
+        };
+    }
+
+    #[test]
+    fn runs_int() {
+        assert_runs!(vec![Instruction::Int(1)], 1);
+    }
+
+    #[test]
+    fn runs_add() {
+        assert_runs!(
+            vec![
+                Instruction::Int(1),
+                Instruction::Int(2),
+                Instruction::Binary(BinaryOp::Add)
+            ],
+            3,
+        );
+
+        // overflow
+        assert_runs!(
+            vec![
+                Instruction::Int(2147483647),
+                Instruction::Int(1),
+                Instruction::Binary(BinaryOp::Add),
+            ],
+            -2147483648,
+        );
+    }
+
+    #[test]
+    fn runs_sub() {
+        assert_runs!(
+            vec![
+                Instruction::Int(3),
+                Instruction::Int(2),
+                Instruction::Binary(BinaryOp::Sub)
+            ],
+            1,
+        );
+
+        // overflow
+        assert_runs!(
+            vec![
+                Instruction::Int(-2147483648),
+                Instruction::Int(1),
+                Instruction::Binary(BinaryOp::Sub),
+            ],
+            2147483647,
+        );
+    }
+
+    #[test]
+    fn runs_mul() {
+        assert_runs!(
+            vec![
+                Instruction::Int(2),
+                Instruction::Int(3),
+                Instruction::Binary(BinaryOp::Mul)
+            ],
+            6,
+        );
+
+        // overflow
+        assert_runs!(
+            vec![
+                Instruction::Int(-2147483648),
+                Instruction::Int(-1),
+                Instruction::Binary(BinaryOp::Mul),
+            ],
+            -2147483648,
+        );
+    }
+
+    #[test]
+    fn runs_div() {
+        assert_runs!(
+            vec![
+                Instruction::Int(6),
+                Instruction::Int(3),
+                Instruction::Binary(BinaryOp::Div)
+            ],
+            2,
+        );
+
+        // overflow
+        assert_runs!(
+            vec![
+                Instruction::Int(-2147483648),
+                Instruction::Int(-1),
+                Instruction::Binary(BinaryOp::Div),
+            ],
+            -2147483648,
+        );
+
+        // division by zero
+        assert_does_not_run!(
+            vec![
+                Instruction::Int(1),
+                Instruction::Int(0),
+                Instruction::Binary(BinaryOp::Div)
+            ],
+            Error::new(
+                SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(5, 1, 6)),
+                "division by zero",
+            ),
+        );
+    }
+}
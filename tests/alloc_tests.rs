@@ -0,0 +1,36 @@
+// The allocator counts allocations of the whole test binary, so this file
+// contains just one test.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn evals_in_one_pass_without_allocating() {
+    let input = "/* € */ (1 + 2) * 3 -\n// 🦀\n(4 + 5) * (6 - 7) / 2";
+    let expected = sari::eval(input);
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = sari::eval_one_pass(input);
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+
+    assert_eq!(result, expected);
+    assert_eq!(after - before, 0);
+}
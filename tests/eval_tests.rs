@@ -1,4 +1,6 @@
-use sari::{Error, SourcePos, SourceSpan};
+use std::thread;
+
+use sari::{Engine, Error, Limit, Limits, SourceDb, SourcePos, SourceSpan, TokenKind};
 
 #[test]
 fn evals_valid_expressions() {
@@ -20,3 +22,462 @@ fn reports_evaluator_errors() {
 
     assert_eq!(sari::eval("1 / 0"), Err(error));
 }
+
+#[test]
+fn evals_parsed_expressions() {
+    let expr = sari::parse("(1 + 2) * 3").unwrap();
+
+    assert_eq!(expr.eval(), Ok(9));
+    assert_eq!(expr.optimize().eval(), Ok(9));
+}
+
+#[test]
+fn reports_evaluator_errors_in_optimized_expressions() {
+    let span = SourceSpan::new(SourcePos::new(1, 1, 2), SourcePos::new(6, 1, 7));
+    let error = Error::new(span, "division by zero");
+
+    let expr = sari::parse("(1 / 0) * 0").unwrap();
+
+    assert_eq!(expr.optimize().eval(), Err(error));
+}
+
+#[test]
+fn evals_bytecode() {
+    let expr = sari::parse("(1 + 2) * 3").unwrap();
+
+    assert_eq!(expr.to_bytecode().eval(), Ok(9));
+}
+
+#[test]
+fn reports_evaluator_errors_in_bytecode() {
+    let span = SourceSpan::new(SourcePos::new(5, 1, 6), SourcePos::new(10, 1, 11));
+    let error = Error::new(span, "division by zero");
+
+    let expr = sari::parse("1 + (2 / 0)").unwrap();
+
+    assert_eq!(expr.to_bytecode().eval(), Err(error));
+}
+
+#[test]
+fn evals_compiled_expressions() {
+    let expr = sari::parse("(1 + 2) * 3").unwrap();
+
+    assert_eq!(expr.compile().eval(), Ok(9));
+}
+
+#[test]
+fn reports_evaluator_errors_in_compiled_expressions() {
+    let span = SourceSpan::new(SourcePos::new(5, 1, 6), SourcePos::new(10, 1, 11));
+    let error = Error::new(span, "division by zero");
+
+    let expr = sari::parse("1 + (2 / 0)").unwrap();
+
+    assert_eq!(expr.compile().eval(), Err(error));
+}
+
+#[test]
+fn reports_deeply_nested_expressions() {
+    let span = SourceSpan::new(SourcePos::new(256, 1, 257), SourcePos::new(257, 1, 258));
+    let error = Error::limit_exceeded(span, Limit::Depth);
+
+    assert_eq!(sari::eval(&"(".repeat(100000)), Err(error));
+}
+
+#[test]
+fn evals_expressions_nested_up_to_max_depth() {
+    let input = format!("{}1 + 2{}", "(".repeat(256), ")".repeat(256));
+    let expr = sari::parse(&input).unwrap();
+
+    assert_eq!(expr.eval(), Ok(3));
+    assert_eq!(expr.optimize().eval(), Ok(3));
+    assert_eq!(expr.to_bytecode().eval(), Ok(3));
+    assert_eq!(expr.compile().eval(), Ok(3));
+    assert_eq!(sari::eval_one_pass(&input), Ok(3));
+}
+
+#[test]
+fn evals_long_chains_of_operators() {
+    let input = vec!["1"; 10000].join(" + ");
+    let expr = sari::parse(&input).unwrap();
+
+    assert_eq!(expr.eval(), Ok(10000));
+    assert_eq!(expr.eval_traced().result(), Ok(10000));
+    assert_eq!(expr.optimize().eval(), Ok(10000));
+    assert_eq!(expr.to_bytecode().eval(), Ok(10000));
+    assert_eq!(expr.compile().eval(), Ok(10000));
+    assert_eq!(sari::eval_one_pass(&input), Ok(10000));
+
+    assert_eq!(expr.ast().to_string(), input);
+    assert_eq!(sari::parse_cst(&input).unwrap().to_string(), input);
+    assert!(sari::format(&input).is_ok());
+}
+
+#[test]
+fn optimizes_long_chains_of_operators() {
+    // Each multiplication by zero checks whether its left operand can fail.
+    let input = format!("(1 / 0){}", " * 0".repeat(20000));
+    let expr = sari::parse(&input).unwrap();
+
+    assert_eq!(expr.optimize().eval(), expr.eval());
+    assert_eq!(expr.eval().unwrap_err().message(), "division by zero");
+
+    let input = format!("(1 + 2){}", " * 0".repeat(20000));
+    let expr = sari::parse(&input).unwrap();
+
+    assert_eq!(expr.optimize().ast().to_string(), "0");
+}
+
+#[test]
+fn reports_exceeded_limits() {
+    let limits = Limits {
+        max_input_len: 5,
+        ..Limits::default()
+    };
+    let span = SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(0, 1, 1));
+    let error = Error::limit_exceeded(span, Limit::InputLen);
+
+    assert!(sari::parse_with_limits("1 + 2", limits).is_ok());
+    assert_eq!(
+        sari::parse_with_limits("1 + 22", limits).unwrap_err(),
+        error
+    );
+
+    let limits = Limits {
+        max_tokens: 3,
+        ..Limits::default()
+    };
+    let span = SourceSpan::new(SourcePos::new(6, 1, 7), SourcePos::new(7, 1, 8));
+    let error = Error::limit_exceeded(span, Limit::Tokens);
+
+    assert!(sari::parse_with_limits("1 + 2", limits).is_ok());
+    assert_eq!(
+        sari::parse_with_limits("1 + 2 + 3", limits).unwrap_err(),
+        error
+    );
+
+    let limits = Limits {
+        max_nodes: 3,
+        ..Limits::default()
+    };
+    let span = SourceSpan::new(SourcePos::new(8, 1, 9), SourcePos::new(9, 1, 10));
+    let error = Error::limit_exceeded(span, Limit::Nodes);
+
+    assert!(sari::parse_with_limits("1 + 2", limits).is_ok());
+    assert_eq!(
+        sari::parse_with_limits("1 + 2 + 3", limits).unwrap_err(),
+        error
+    );
+
+    let limits = Limits {
+        max_steps: 3,
+        ..Limits::default()
+    };
+    let span = SourceSpan::new(SourcePos::new(4, 1, 5), SourcePos::new(5, 1, 6));
+    let error = Error::limit_exceeded(span, Limit::Steps);
+
+    assert_eq!(
+        sari::parse_with_limits("1 + 2", limits).unwrap().eval(),
+        Ok(3)
+    );
+    assert_eq!(
+        sari::parse_with_limits("1 + 2 + 3", limits).unwrap().eval(),
+        Err(error)
+    );
+}
+
+#[test]
+fn enforces_steps_limit_only_when_evaluating_the_ast() {
+    let limits = Limits {
+        max_steps: 3,
+        ..Limits::default()
+    };
+    let expr = sari::parse_with_limits("1 + 2 + 3", limits).unwrap();
+
+    assert_eq!(expr.eval().unwrap_err().limit(), Some(Limit::Steps));
+    assert_eq!(
+        expr.eval_traced().result().unwrap_err().limit(),
+        Some(Limit::Steps)
+    );
+
+    // The optimized expression is a single literal, which takes one step.
+    assert_eq!(expr.optimize().eval(), Ok(6));
+    assert_eq!(expr.to_bytecode().eval(), Ok(6));
+    assert_eq!(expr.compile().eval(), Ok(6));
+}
+
+#[test]
+fn tokenizes_invalid_expressions() {
+    let tokens = sari::tokenize("1 +\n?").collect::<Vec<_>>();
+
+    assert_eq!(tokens.len(), 3);
+    assert_eq!(tokens[2].kind(), TokenKind::Error);
+    assert_eq!(
+        tokens[2].span(),
+        SourceSpan::new(SourcePos::new(4, 2, 1), SourcePos::new(5, 2, 2)),
+    );
+}
+
+#[test]
+fn round_trips_concrete_syntax_trees() {
+    let input = " ( 1 +\t2 )\r\n* 3\n";
+    let cst = sari::parse_cst(input).unwrap();
+
+    assert_eq!(cst.to_string(), input);
+    assert_eq!(cst.ast(), sari::parse(input).unwrap().ast());
+}
+
+#[test]
+fn evals_expressions_with_comments() {
+    assert_eq!(sari::eval("1 + // one\n2 /* two */ * 3"), Ok(7));
+}
+
+#[test]
+fn reports_unterminated_block_comments() {
+    let span = SourceSpan::new(SourcePos::new(4, 1, 5), SourcePos::new(12, 2, 3));
+    let error = Error::new(span, "unterminated block comment");
+
+    assert_eq!(sari::eval("1 + /* two\n*"), Err(error));
+}
+
+#[test]
+fn formats_expressions() {
+    let inputs = [
+        "1+2*3",
+        "((1 + 2)) * 3 // nine",
+        "1 - (2 - 3) / (4 * 5)",
+        "// a\n(1 /* b */ + 2) // c\n* 3\n// d",
+    ];
+
+    for input in inputs {
+        let formatted = sari::format(input).unwrap();
+
+        // formatting keeps the meaning and is idempotent
+        assert_eq!(sari::eval(&formatted), sari::eval(input));
+        assert_eq!(sari::format(&formatted).unwrap(), formatted);
+    }
+}
+
+#[test]
+fn displays_asts_as_valid_expressions() {
+    let inputs = [
+        "((1 + 2)) * 3",
+        "1 - (2 - 3)",
+        "(1 * 2) + 3",
+        "1 - 5 * 2 - 3",
+    ];
+
+    for input in inputs {
+        let expr = sari::parse(input).unwrap();
+        let optimized = expr.optimize();
+
+        // optimized trees contain negative literals
+        for ast in [expr.ast(), optimized.ast()] {
+            assert_eq!(sari::eval(&ast.to_string()), expr.eval());
+        }
+    }
+
+    assert_eq!(
+        sari::parse("(1 * 2) + 3").unwrap().ast().to_string(),
+        "1 * 2 + 3"
+    );
+    assert_eq!(
+        sari::parse("1 - 5 * 2")
+            .unwrap()
+            .optimize()
+            .ast()
+            .to_string(),
+        "0 - 9"
+    );
+}
+
+#[test]
+fn traces_evaluation() {
+    let trace = sari::eval_traced("(1 + 2) * 3").unwrap();
+
+    assert_eq!(trace.result(), Ok(9));
+    assert_eq!(
+        trace
+            .reductions()
+            .iter()
+            .map(|reduction| reduction.value())
+            .collect::<Vec<_>>(),
+        vec![3, 9],
+    );
+    assert_eq!(trace.to_string(), "(1 + 2) * 3 → 3 * 3 → 9");
+
+    let trace = sari::eval_traced("2 * (1 / 0)").unwrap();
+    assert_eq!(trace.result(), sari::eval("2 * (1 / 0)"));
+    assert!(trace.reductions().is_empty());
+
+    assert!(sari::eval_traced("(1 + 2").is_err());
+}
+
+#[test]
+fn reports_byte_offsets_and_utf16_columns() {
+    let input = "// 🦀\n/* é */ 1 / 0";
+    let error = sari::eval(input).unwrap_err();
+
+    let start = error.span().start();
+    assert_eq!(start.offset(), 13);
+    assert_eq!((start.line(), start.column()), (2, 9));
+    assert_eq!(start.byte_offset(), 17);
+    assert_eq!(start.utf16_column(), 9);
+    assert_eq!(error.span().text(input), "1 / 0");
+
+    let input = "/* 🦀 */ 1 +";
+    let error = sari::eval(input).unwrap_err();
+
+    let start = error.span().start();
+    assert_eq!(start.column(), 12);
+    assert_eq!(start.utf16_column(), 13);
+    assert_eq!(error.span().text(input), "");
+}
+
+#[test]
+fn reports_errors_with_source_names() {
+    let mut db = SourceDb::new();
+    let base = db.add("base.sari", "1 + 2");
+    let pricing = db.add("pricing.sari", "// discount\n\n1 + (2 + 3) / 0");
+
+    assert_eq!(db.eval(base), Ok(3));
+
+    let error = db.eval(pricing).unwrap_err();
+    assert_eq!(error.source_id(), Some(pricing));
+    assert_eq!(error.source_name(), Some("pricing.sari"));
+    assert_eq!(error.to_string(), "pricing.sari:3:5-3:16: division by zero");
+
+    assert_eq!(sari::eval("1 / 0").unwrap_err().source_id(), None);
+}
+
+#[test]
+fn evals_expressions_across_threads() {
+    let engine = Engine::new().with_limits(Limits {
+        max_tokens: 100,
+        ..Limits::default()
+    });
+    let expr = engine.parse("(1 + 2) * 3").unwrap();
+
+    thread::scope(|scope| {
+        let handles = (0..4)
+            .map(|i| {
+                let expr = &expr;
+                let engine = &engine;
+
+                scope.spawn(move || (expr.eval(), engine.eval(&format!("{i} * 2"))))
+            })
+            .collect::<Vec<_>>();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), (Ok(9), Ok(i as i32 * 2)));
+        }
+    });
+
+    let expr = thread::spawn(move || engine.parse("1 / 0"))
+        .join()
+        .unwrap()
+        .unwrap();
+    assert_eq!(expr.eval(), sari::eval("1 / 0"));
+}
+
+#[test]
+fn evals_compiled_expressions_across_threads() {
+    let mut db = SourceDb::new();
+    let id = db.add("formula.sari", "// total\n(1 + 2) * 3 / (4 - 4)");
+    let compiled = db.parse(id).unwrap().compile();
+
+    let errors = thread::scope(|scope| {
+        let handles = (0..4)
+            .map(|_| scope.spawn(|| compiled.eval()))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap().unwrap_err().to_string())
+            .collect::<Vec<_>>()
+    });
+
+    assert_eq!(errors, vec!["formula.sari:2:1-2:22: division by zero"; 4]);
+}
+
+// Simple xorshift generator, so that the corpus below is deterministic.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.next() as usize % items.len()]
+    }
+}
+
+fn generate_expr(rng: &mut Rng, buf: &mut String, depth: u32) {
+    const INTS: &[&str] = &["0", "1", "2", "7", "10", "2147483647", "2147483648"];
+    const OPS: &[&str] = &[" + ", " - ", " * ", " / ", "+", "/"];
+    const TRIVIA: &[&str] = &["", "", "", " ", "\n", "// €\n", "/* 🦀 */"];
+
+    buf.push_str(rng.pick(TRIVIA));
+    match rng.next() % 4 {
+        0 if depth > 0 => {
+            buf.push('(');
+            generate_expr(rng, buf, depth - 1);
+            buf.push(')');
+        }
+        1 | 2 if depth > 0 => {
+            generate_expr(rng, buf, depth - 1);
+            buf.push_str(rng.pick(OPS));
+            generate_expr(rng, buf, depth - 1);
+        }
+        _ => buf.push_str(rng.pick(INTS)),
+    }
+    buf.push_str(rng.pick(TRIVIA));
+}
+
+fn generate_tokens(rng: &mut Rng, buf: &mut String) {
+    const TOKENS: &[&str] = &[
+        "0", "1", "3", "+", "-", "*", "/", "(", "(", ")", ")", " ", "\n", "é", "%", "/*", "*/",
+        "//",
+    ];
+
+    for _ in 0..rng.next() % 16 {
+        buf.push_str(rng.pick(TOKENS));
+    }
+}
+
+#[test]
+fn evals_in_one_pass_like_tree_based_evaluation() {
+    let mut rng = Rng(0x5eed_5a41);
+    let mut corpus = vec![];
+
+    for _ in 0..20_000 {
+        let mut buf = String::new();
+        if rng.next().is_multiple_of(4) {
+            generate_tokens(&mut rng, &mut buf);
+        } else {
+            generate_expr(&mut rng, &mut buf, 6);
+
+            // Corrupt some valid expressions by cutting them short.
+            if rng.next().is_multiple_of(8) {
+                let end = buf.char_indices().count() / 2;
+                buf = buf.chars().take(end).collect();
+            }
+        }
+
+        corpus.push(buf);
+    }
+
+    for depth in [255, 256, 257] {
+        corpus.push(format!("{}1{}", "(".repeat(depth), ")".repeat(depth)));
+        corpus.push(format!("1{}", " + 1".repeat(depth)));
+        corpus.push(format!("1 / 0{}", " * 1".repeat(depth)));
+        corpus.push(format!("{}1 / 0", "(1 + ".repeat(depth)));
+    }
+
+    for input in &corpus {
+        assert_eq!(sari::eval_one_pass(input), sari::eval(input), "{input:?}");
+    }
+}
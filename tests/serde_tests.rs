@@ -0,0 +1,168 @@
+#![cfg(feature = "serde")]
+
+use sari::ast::Ast;
+use sari::{Error, Limit, Limits, SourceDb, SourcePos, SourceSpan, Span};
+use serde_json::json;
+
+#[test]
+fn serializes_asts() {
+    let expr = sari::parse("(1 + 2) * 3").unwrap();
+
+    assert_eq!(
+        serde_json::to_value(expr.ast()).unwrap(),
+        json!({
+            "exprs": [
+                { "kind": "int", "span": { "start": 1, "end": 2 }, "value": 1 },
+                { "kind": "int", "span": { "start": 5, "end": 6 }, "value": 2 },
+                {
+                    "kind": "binary",
+                    "span": { "start": 1, "end": 6 },
+                    "op": "add",
+                    "left": 0,
+                    "right": 1
+                },
+                { "kind": "group", "span": { "start": 0, "end": 7 }, "expr": 2 },
+                { "kind": "int", "span": { "start": 10, "end": 11 }, "value": 3 },
+                {
+                    "kind": "binary",
+                    "span": { "start": 0, "end": 11 },
+                    "op": "mul",
+                    "left": 3,
+                    "right": 4
+                }
+            ]
+        }),
+    );
+}
+
+#[test]
+fn round_trips_asts() {
+    let inputs = ["1", "(1)", "1 + 2 * 3", "(1 - 2) / (3 - 4)", "2147483648"];
+
+    for input in inputs {
+        let ast = sari::parse(input).unwrap().ast().clone();
+        let json = serde_json::to_string(&ast).unwrap();
+
+        assert_eq!(serde_json::from_str::<Ast>(&json).unwrap(), ast);
+    }
+}
+
+#[test]
+fn does_not_deserialize_invalid_asts() {
+    let forward = json!({
+        "exprs": [
+            { "kind": "group", "span": { "start": 0, "end": 3 }, "expr": 1 },
+            { "kind": "int", "span": { "start": 1, "end": 2 }, "value": 1 }
+        ]
+    });
+    let cyclic = json!({
+        "exprs": [
+            { "kind": "group", "span": { "start": 0, "end": 3 }, "expr": 0 }
+        ]
+    });
+    let unknown_kind = json!({
+        "exprs": [
+            { "kind": "float", "span": { "start": 0, "end": 3 }, "value": 1.5 }
+        ]
+    });
+
+    for json in [forward, cyclic, unknown_kind] {
+        assert!(serde_json::from_value::<Ast>(json).is_err());
+    }
+}
+
+#[test]
+fn round_trips_spans() {
+    let span = Span::new(1, 5);
+    let json = json!({ "start": 1, "end": 5 });
+
+    assert_eq!(serde_json::to_value(span).unwrap(), json);
+    assert_eq!(serde_json::from_value::<Span>(json).unwrap(), span);
+
+    let span = SourceSpan::new(SourcePos::new(0, 1, 1), SourcePos::new(5, 2, 2));
+    let json = json!({
+        "start": { "offset": 0, "line": 1, "column": 1, "byte_offset": 0, "utf16_column": 1 },
+        "end": { "offset": 5, "line": 2, "column": 2, "byte_offset": 5, "utf16_column": 2 }
+    });
+
+    assert_eq!(serde_json::to_value(span).unwrap(), json);
+    assert_eq!(serde_json::from_value::<SourceSpan>(json).unwrap(), span);
+}
+
+#[test]
+fn round_trips_errors() {
+    let error = sari::eval("1 / 0").unwrap_err();
+    let json = json!({
+        "span": {
+            "start": { "offset": 0, "line": 1, "column": 1, "byte_offset": 0, "utf16_column": 1 },
+            "end": { "offset": 5, "line": 1, "column": 6, "byte_offset": 5, "utf16_column": 6 }
+        },
+        "message": "division by zero",
+        "limit": null
+    });
+
+    assert_eq!(serde_json::to_value(&error).unwrap(), json);
+    assert_eq!(serde_json::from_value::<Error>(json).unwrap(), error);
+
+    let limits = Limits {
+        max_depth: 1,
+        ..Limits::default()
+    };
+    let error = sari::parse_with_limits("((1))", limits).unwrap_err();
+    let json = serde_json::to_value(&error).unwrap();
+
+    assert_eq!(json["limit"], "depth");
+    assert_eq!(
+        serde_json::from_value::<Error>(json).unwrap().limit(),
+        Some(Limit::Depth)
+    );
+}
+
+#[test]
+fn round_trips_errors_with_sources() {
+    let mut db = SourceDb::new();
+    let id = db.add("pricing.sari", "1 / 0");
+    let error = db.eval(id).unwrap_err();
+    let json = serde_json::to_value(&error).unwrap();
+
+    assert_eq!(json["source"], json!({ "id": 0, "name": "pricing.sari" }));
+    assert_eq!(serde_json::from_value::<Error>(json).unwrap(), error);
+}
+
+// Pins the exact JSON, including the field order, so that changes to the
+// shape show up in review.
+#[test]
+fn serializes_errors_to_json() {
+    let mut db = SourceDb::new();
+    let id = db.add("pricing.sari", "// 𝑥\n1 / 0");
+    let error = db.eval(id).unwrap_err();
+
+    assert_eq!(
+        serde_json::to_string(&error).unwrap(),
+        concat!(
+            r#"{"span":{"#,
+            r#""start":{"offset":5,"line":2,"column":1,"byte_offset":8,"utf16_column":1},"#,
+            r#""end":{"offset":10,"line":2,"column":6,"byte_offset":13,"utf16_column":6}},"#,
+            r#""message":"division by zero","#,
+            r#""limit":null,"#,
+            r#""source":{"id":0,"name":"pricing.sari"}}"#,
+        ),
+    );
+
+    let limits = Limits {
+        max_depth: 1,
+        ..Limits::default()
+    };
+    let error = sari::parse_with_limits("((1))", limits).unwrap_err();
+
+    assert_eq!(
+        serde_json::to_string(&error).unwrap(),
+        concat!(
+            r#"{"span":{"#,
+            r#""start":{"offset":1,"line":1,"column":2,"byte_offset":1,"utf16_column":2},"#,
+            r#""end":{"offset":2,"line":1,"column":3,"byte_offset":2,"utf16_column":3}},"#,
+            r#""message":"expression nested too deeply","#,
+            r#""limit":"depth"}"#,
+        ),
+    );
+}